@@ -0,0 +1,585 @@
+//! Compact binary encoding of trace events.
+//!
+//! Each event is encoded with borsh as an `EventRecord`: a one byte variant
+//! tag followed by its fields, with every variable-sized field prefixed by
+//! its little-endian `u32` length.
+//!
+//! Memory is recorded as raw bytes, or as a `SparseMemory` with the
+//! `sparse-memory-trace` feature.
+//!
+//! `RingBufferSink` keeps fixed-size records of the last events of an
+//! execution, for post-mortem debugging where an unbounded trace does not
+//! fit.
+
+use alloc::vec::Vec;
+use borsh::{BorshSerialize, BorshDeserialize};
+use crate::{H160, U256, Context, Opcode, Memory, Capture, ExitReason, Trap};
+use crate::tracing::Event;
+
+/// Memory as recorded in `EventRecord`s.
+#[cfg(not(feature = "sparse-memory-trace"))]
+pub type TraceMemory = Vec<u8>;
+/// Memory as recorded in `EventRecord`s.
+#[cfg(feature = "sparse-memory-trace")]
+pub type TraceMemory = SparseMemory;
+
+#[cfg(not(feature = "sparse-memory-trace"))]
+fn trace_memory(memory: &Memory) -> TraceMemory {
+	memory.data().to_vec()
+}
+
+#[cfg(feature = "sparse-memory-trace")]
+fn trace_memory(memory: &Memory) -> TraceMemory {
+	SparseMemory::from_bytes(memory.data())
+}
+
+/// Memory bytes as the list of their 32-byte words that are not all zeros.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct SparseMemory {
+	/// Length of the memory, in bytes.
+	pub len: usize,
+	/// Non-zero words with their byte offset, in ascending order. The last
+	/// word is zero padded if the length is not a multiple of 32.
+	pub words: Vec<(usize, [u8; 32])>,
+}
+
+impl SparseMemory {
+	/// Encode memory bytes.
+	#[must_use]
+	pub fn from_bytes(bytes: &[u8]) -> Self {
+		let words = bytes.chunks(32).enumerate()
+			.filter(|(_, chunk)| chunk.iter().any(|byte| *byte != 0))
+			.map(|(index, chunk)| {
+				let mut word = [0_u8; 32];
+				word[..chunk.len()].copy_from_slice(chunk);
+				(index * 32, word)
+			})
+			.collect();
+
+		Self { len: bytes.len(), words }
+	}
+
+	/// Decode the memory bytes.
+	#[must_use]
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut bytes = alloc::vec![0_u8; self.len + 32];
+		for (offset, word) in &self.words {
+			bytes[*offset..*offset + 32].copy_from_slice(word);
+		}
+		bytes.truncate(self.len);
+		bytes
+	}
+}
+
+/// Owned form of an `Event`, as decoded from the binary format.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(BorshSerialize, BorshDeserialize)]
+pub enum EventRecord {
+	/// See `Event::Step`.
+	Step {
+		/// Context.
+		context: Context,
+		/// Opcode.
+		opcode: Opcode,
+		/// Program counter.
+		position: Result<usize, ExitReason>,
+		/// Stack, from bottom to top.
+		stack: Vec<U256>,
+		/// Memory.
+		memory: TraceMemory,
+	},
+	/// See `Event::StepResult`.
+	StepResult {
+		/// Step result.
+		result: Result<(), Capture<ExitReason, Trap>>,
+		/// Return value.
+		return_value: Vec<u8>,
+		/// Stack, from bottom to top.
+		stack: Vec<U256>,
+		/// Memory.
+		memory: TraceMemory,
+	},
+	/// See `Event::StepStackDelta`.
+	StepStackDelta {
+		/// Number of items popped.
+		popped: u8,
+		/// Items pushed, from bottom to top.
+		pushed: Vec<U256>,
+	},
+	/// See `Event::SLoad`.
+	SLoad {
+		/// Address.
+		address: H160,
+		/// Index.
+		index: U256,
+		/// Value.
+		value: U256,
+	},
+	/// See `Event::SStore`.
+	SStore {
+		/// Address.
+		address: H160,
+		/// Index.
+		index: U256,
+		/// Value.
+		value: U256,
+	},
+	/// See `Event::StorageDiff`.
+	StorageDiff {
+		/// Address.
+		address: H160,
+		/// Changes, as `(index, old, new)`.
+		changes: Vec<(U256, U256, U256)>,
+	},
+	/// See `Event::TransactionResult`.
+	TransactionResult {
+		/// Gas used before the refund.
+		gas_used: u64,
+		/// Refund applied.
+		gas_refunded: u64,
+		/// Gas used after the refund.
+		gas_used_after_refund: u64,
+		/// Exit reason of the top-level frame.
+		exit_reason: ExitReason,
+	},
+	/// See `Event::IncrementNonce`.
+	IncrementNonce {
+		/// Address.
+		address: H160,
+	},
+	/// See `Event::FrameGas`.
+	FrameGas {
+		/// Call depth.
+		depth: usize,
+		/// Gas used, children included.
+		gas_used: u64,
+		/// Gas used by children.
+		gas_used_children: u64,
+	},
+	/// See `Event::Custom`.
+	Custom {
+		/// Tag.
+		tag: u32,
+		/// Data.
+		data: Vec<u8>,
+	},
+}
+
+impl<'a> From<&Event<'a>> for EventRecord {
+	fn from(event: &Event<'a>) -> Self {
+		match event {
+			Event::Step(trace) => Self::Step {
+				context: trace.context.clone(),
+				opcode: trace.opcode,
+				position: *trace.position,
+				stack: trace.stack.data().to_vec(),
+				memory: trace_memory(trace.memory),
+			},
+			Event::StepResult(trace) => Self::StepResult {
+				result: *trace.result,
+				return_value: trace.return_value.clone(),
+				stack: trace.stack.data().to_vec(),
+				memory: trace_memory(trace.memory),
+			},
+			Event::StepStackDelta(trace) => Self::StepStackDelta {
+				popped: trace.popped,
+				pushed: trace.pushed.to_vec(),
+			},
+			Event::SLoad(trace) => Self::SLoad {
+				address: trace.address,
+				index: trace.index,
+				value: trace.value,
+			},
+			Event::SStore(trace) => Self::SStore {
+				address: trace.address,
+				index: trace.index,
+				value: trace.value,
+			},
+			Event::StorageDiff(trace) => Self::StorageDiff {
+				address: trace.address,
+				changes: trace.changes.clone(),
+			},
+			Event::TransactionResult(trace) => Self::TransactionResult {
+				gas_used: trace.gas_used,
+				gas_refunded: trace.gas_refunded,
+				gas_used_after_refund: trace.gas_used_after_refund,
+				exit_reason: trace.exit_reason,
+			},
+			Event::IncrementNonce(trace) => Self::IncrementNonce {
+				address: trace.address,
+			},
+			Event::FrameGas(trace) => Self::FrameGas {
+				depth: trace.depth,
+				gas_used: trace.gas_used,
+				gas_used_children: trace.gas_used_children,
+			},
+			Event::Custom(trace) => Self::Custom {
+				tag: trace.tag,
+				data: trace.data.to_vec(),
+			},
+		}
+	}
+}
+
+/// Encode an event in the binary trace format.
+#[must_use]
+pub fn encode_event(event: &Event) -> Vec<u8> {
+	EventRecord::from(event).try_to_vec().expect("encoding into a vector never fails; qed")
+}
+
+/// Decode an event previously encoded with `encode_event`.
+pub fn decode_event(bytes: &[u8]) -> borsh::maybestd::io::Result<EventRecord> {
+	EventRecord::try_from_slice(bytes)
+}
+
+/// Number of stack items, from the top, kept by a `TailRecord::Step`.
+pub const TAIL_STACK_ITEMS: usize = 4;
+
+/// Fixed-size summary of an `Event`, as kept by `RingBufferSink`.
+///
+/// Variable-sized fields are reduced to their length or a bounded part, so
+/// that recording never allocates. Steps carry no gas, which the machine
+/// does not know; the gas of a frame is in its `FrameGas` record.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TailRecord {
+	/// See `Event::Step`.
+	Step {
+		/// Opcode.
+		opcode: Opcode,
+		/// Program counter.
+		position: Result<usize, ExitReason>,
+		/// Stack depth.
+		stack_len: usize,
+		/// Up to `TAIL_STACK_ITEMS` items from the top of the stack, top
+		/// first, padded with zeros.
+		stack_top: [U256; TAIL_STACK_ITEMS],
+	},
+	/// See `Event::StepResult`.
+	StepResult {
+		/// Step result.
+		result: Result<(), Capture<ExitReason, Trap>>,
+		/// Length of the return value.
+		return_len: usize,
+	},
+	/// See `Event::StepStackDelta`.
+	StepStackDelta {
+		/// Number of items popped.
+		popped: u8,
+		/// Number of items pushed.
+		pushed: usize,
+	},
+	/// See `Event::SLoad`.
+	SLoad {
+		/// Address.
+		address: H160,
+		/// Index.
+		index: U256,
+		/// Value.
+		value: U256,
+	},
+	/// See `Event::SStore`.
+	SStore {
+		/// Address.
+		address: H160,
+		/// Index.
+		index: U256,
+		/// Value.
+		value: U256,
+	},
+	/// See `Event::StorageDiff`.
+	StorageDiff {
+		/// Address.
+		address: H160,
+		/// Number of changes.
+		changes: usize,
+	},
+	/// See `Event::TransactionResult`.
+	TransactionResult {
+		/// Gas used before the refund.
+		gas_used: u64,
+		/// Refund applied.
+		gas_refunded: u64,
+		/// Gas used after the refund.
+		gas_used_after_refund: u64,
+		/// Exit reason of the top-level frame.
+		exit_reason: ExitReason,
+	},
+	/// See `Event::IncrementNonce`.
+	IncrementNonce {
+		/// Address.
+		address: H160,
+	},
+	/// See `Event::FrameGas`.
+	FrameGas {
+		/// Call depth.
+		depth: usize,
+		/// Gas used, children included.
+		gas_used: u64,
+		/// Gas used by children.
+		gas_used_children: u64,
+	},
+	/// See `Event::Custom`.
+	Custom {
+		/// Tag.
+		tag: u32,
+		/// Length of the data.
+		data_len: usize,
+	},
+}
+
+impl<'a> From<&Event<'a>> for TailRecord {
+	fn from(event: &Event<'a>) -> Self {
+		match event {
+			Event::Step(trace) => {
+				let stack = trace.stack.data();
+				let mut stack_top = [U256::zero(); TAIL_STACK_ITEMS];
+				for (slot, value) in stack_top.iter_mut().zip(stack.iter().rev()) {
+					*slot = *value;
+				}
+				Self::Step { opcode: trace.opcode, position: *trace.position, stack_len: stack.len(), stack_top }
+			},
+			Event::StepResult(trace) => Self::StepResult {
+				result: *trace.result,
+				return_len: trace.return_value.len(),
+			},
+			Event::StepStackDelta(trace) => Self::StepStackDelta {
+				popped: trace.popped,
+				pushed: trace.pushed.len(),
+			},
+			Event::SLoad(trace) => Self::SLoad {
+				address: trace.address,
+				index: trace.index,
+				value: trace.value,
+			},
+			Event::SStore(trace) => Self::SStore {
+				address: trace.address,
+				index: trace.index,
+				value: trace.value,
+			},
+			Event::StorageDiff(trace) => Self::StorageDiff {
+				address: trace.address,
+				changes: trace.changes.len(),
+			},
+			Event::TransactionResult(trace) => Self::TransactionResult {
+				gas_used: trace.gas_used,
+				gas_refunded: trace.gas_refunded,
+				gas_used_after_refund: trace.gas_used_after_refund,
+				exit_reason: trace.exit_reason,
+			},
+			Event::IncrementNonce(trace) => Self::IncrementNonce {
+				address: trace.address,
+			},
+			Event::FrameGas(trace) => Self::FrameGas {
+				depth: trace.depth,
+				gas_used: trace.gas_used,
+				gas_used_children: trace.gas_used_children,
+			},
+			Event::Custom(trace) => Self::Custom {
+				tag: trace.tag,
+				data_len: trace.data.len(),
+			},
+		}
+	}
+}
+
+/// Buffer keeping `TailRecord`s of the last `N` events in a fixed array,
+/// overwriting the oldest.
+///
+/// Recording never allocates, so the buffer fits on-chain environments.
+/// The buffer is fed through `record`: a trace sink trait to implement
+/// does not exist yet, and `EventListener`s are expected to forward to it.
+pub struct RingBufferSink<const N: usize> {
+	records: [Option<TailRecord>; N],
+	/// Number of events received.
+	total: usize,
+}
+
+impl<const N: usize> RingBufferSink<N> {
+	/// Create an empty buffer.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self { records: [None; N], total: 0 }
+	}
+
+	/// Record `event`, overwriting the oldest record once full.
+	pub fn record(&mut self, event: &Event) {
+		if N == 0 {
+			return;
+		}
+		self.records[self.total % N] = Some(TailRecord::from(event));
+		self.total += 1;
+	}
+
+	/// Number of events received, including overwritten ones.
+	#[must_use]
+	pub const fn total(&self) -> usize {
+		self.total
+	}
+
+	/// Number of records held.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.total.min(N)
+	}
+
+	/// Whether no event was received.
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.total == 0
+	}
+
+	/// Records held, from the oldest to the most recent.
+	pub fn iter(&self) -> impl Iterator<Item = &TailRecord> {
+		let start = if self.total > N { self.total % N } else { 0 };
+		self.records[start..].iter().chain(&self.records[..start]).flatten()
+	}
+}
+
+impl<const N: usize> Default for RingBufferSink<N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::vec;
+	use crate::{H160, U256, Context, Opcode, Stack, Memory, Capture, ExitReason, ExitSucceed};
+	use crate::tracing::{Event, StepTrace, StepResultTrace, StepStackDeltaTrace, SLoadTrace, SStoreTrace, StorageDiffTrace,
+		TransactionResultTrace, IncrementNonceTrace, FrameGasTrace, CustomTrace};
+	use super::{encode_event, decode_event, EventRecord, RingBufferSink, SparseMemory, TailRecord};
+
+	fn round_trip(event: &Event) {
+		let decoded = decode_event(&encode_event(event)).unwrap();
+		assert_eq!(decoded, EventRecord::from(event));
+	}
+
+	#[test]
+	fn test_round_trip() {
+		let context = Context {
+			address: H160::repeat_byte(0xaa),
+			caller: H160::repeat_byte(0xbb),
+			apparent_value: U256::from(7),
+		};
+		let mut stack = Stack::new(1024);
+		stack.push_u256(U256::from(1)).unwrap();
+		stack.push_u256(U256::max_value()).unwrap();
+		let mut memory = Memory::new(1024);
+		memory.set(0, &[1, 2, 3], Some(32)).unwrap();
+
+		round_trip(&Event::Step(StepTrace {
+			context: &context,
+			opcode: Opcode::ADD,
+			position: &Ok(3),
+			stack: &stack,
+			memory: &memory,
+		}));
+		round_trip(&Event::Step(StepTrace {
+			context: &context,
+			opcode: Opcode::STOP,
+			position: &Err(ExitSucceed::Stopped.into()),
+			stack: &stack,
+			memory: &memory,
+		}));
+		for result in [Ok(()), Err(Capture::Exit(ExitReason::StepLimitReached)), Err(Capture::Trap(Opcode::CALL))] {
+			round_trip(&Event::StepResult(StepResultTrace {
+				result: &result,
+				return_value: &vec![4, 5],
+				stack: &stack,
+				memory: &memory,
+			}));
+		}
+		round_trip(&Event::StepStackDelta(StepStackDeltaTrace {
+			popped: 2,
+			pushed: stack.data(),
+		}));
+		round_trip(&Event::SLoad(SLoadTrace {
+			address: H160::repeat_byte(1),
+			index: U256::from(2),
+			value: U256::from(3),
+		}));
+		round_trip(&Event::SStore(SStoreTrace {
+			address: H160::repeat_byte(4),
+			index: U256::from(5),
+			value: U256::from(6),
+		}));
+		round_trip(&Event::StorageDiff(StorageDiffTrace {
+			address: H160::repeat_byte(7),
+			changes: vec![(U256::from(8), U256::zero(), U256::from(9))],
+		}));
+		round_trip(&Event::TransactionResult(TransactionResultTrace {
+			gas_used: 50_000,
+			gas_refunded: 10_000,
+			gas_used_after_refund: 40_000,
+			exit_reason: ExitSucceed::Returned.into(),
+		}));
+		round_trip(&Event::IncrementNonce(IncrementNonceTrace {
+			address: H160::repeat_byte(10),
+		}));
+		round_trip(&Event::FrameGas(FrameGasTrace {
+			depth: 2,
+			gas_used: 30_000,
+			gas_used_children: 20_000,
+		}));
+		round_trip(&Event::Custom(CustomTrace {
+			tag: 0x0c0c,
+			data: &[1, 2, 3],
+		}));
+	}
+
+	#[test]
+	fn sparse_memory_round_trips() {
+		let mut sparse = vec![0_u8; 32 * 100];
+		sparse[40] = 1;
+		sparse[3199] = 2;
+		let cases = [vec![], vec![0; 64], vec![1, 2, 3], vec![0; 33], sparse];
+		for bytes in cases {
+			assert_eq!(SparseMemory::from_bytes(&bytes).to_bytes(), bytes);
+		}
+
+		let mut memory = vec![0_u8; 32 * 100];
+		memory[40] = 1;
+		let encoded = SparseMemory::from_bytes(&memory);
+		assert_eq!(encoded.words.len(), 1);
+		assert_eq!(encoded.words[0].0, 32);
+	}
+
+	#[test]
+	fn ring_buffer_keeps_the_most_recent_events() {
+		let mut sink = RingBufferSink::<3>::new();
+		assert!(sink.is_empty());
+		for i in 0..5_u8 {
+			sink.record(&Event::IncrementNonce(IncrementNonceTrace { address: H160::repeat_byte(i) }));
+		}
+
+		assert_eq!(sink.total(), 5);
+		assert_eq!(sink.len(), 3);
+		let addresses: alloc::vec::Vec<_> = sink.iter().map(|record| match record {
+			TailRecord::IncrementNonce { address } => *address,
+			other => panic!("unexpected record {:?}", other),
+		}).collect();
+		assert_eq!(addresses, vec![H160::repeat_byte(2), H160::repeat_byte(3), H160::repeat_byte(4)]);
+	}
+
+	#[test]
+	fn ring_buffer_keeps_the_top_of_the_stack() {
+		let context = Context { address: H160::zero(), caller: H160::zero(), apparent_value: U256::zero() };
+		let memory = Memory::new(1024);
+		let mut stack = Stack::new(1024);
+		let mut sink = RingBufferSink::<2>::new();
+		for value in 1..=6_u64 {
+			stack.push_u256(U256::from(value)).unwrap();
+			if value == 2 || value == 6 {
+				sink.record(&Event::Step(StepTrace { context: &context, opcode: Opcode::ADD, position: &Ok(0), stack: &stack, memory: &memory }));
+			}
+		}
+
+		let tops: alloc::vec::Vec<_> = sink.iter().map(|record| match record {
+			TailRecord::Step { stack_len, stack_top, .. } => (*stack_len, *stack_top),
+			other => panic!("unexpected record {:?}", other),
+		}).collect();
+		let u = |values: [u64; 4]| values.map(U256::from);
+		assert_eq!(tops, vec![(2, u([2, 1, 0, 0])), (6, u([6, 5, 4, 3]))]);
+	}
+}
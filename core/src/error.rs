@@ -5,6 +5,7 @@ pub type Trap = Opcode;
 
 /// Capture represents the result of execution.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub enum Capture<E, T> {
 	/// The machine has exited. It cannot be executed again.
 	Exit(E),
@@ -56,6 +57,68 @@ impl ExitReason {
 	pub const fn is_fatal(&self) -> bool {
 		matches!(self, Self::Fatal(_))
 	}
+
+	/// Transaction receipt status: 1 on success, 0 otherwise.
+	#[must_use]
+	pub const fn to_receipt_status(&self) -> u8 {
+		if self.is_succeed() { 1 } else { 0 }
+	}
+
+	/// Whether the return data is revert data, meaningful to the caller
+	/// despite the failure. Other failures return no data.
+	#[must_use]
+	pub const fn revert_bytes(&self) -> bool {
+		self.is_revert()
+	}
+
+	/// Stable numeric code of the reason, for persisting it independently of
+	/// the enum layout the serialization derives depend on. The high byte is
+	/// the kind of exit and the low byte the variant; codes are never reused
+	/// and new variants get new ones.
+	#[must_use]
+	pub const fn to_u16(&self) -> u16 {
+		match self {
+			Self::StepLimitReached => 0x0000,
+			Self::Succeed(ExitSucceed::Stopped) => 0x0100,
+			Self::Succeed(ExitSucceed::Returned) => 0x0101,
+			Self::Succeed(ExitSucceed::Suicided) => 0x0102,
+			Self::Error(e) => 0x0200 | e.code() as u16,
+			Self::Revert(ExitRevert::Reverted) => 0x0300,
+			Self::Fatal(ExitFatal::NotSupported) => 0x0400,
+			Self::Fatal(ExitFatal::UnhandledInterrupt) => 0x0401,
+			Self::Fatal(ExitFatal::CodeSizeLimit) => 0x0402,
+			Self::Fatal(ExitFatal::Killed) => 0x0403,
+			Self::Fatal(ExitFatal::ConfigNotSet) => 0x0404,
+			Self::Fatal(ExitFatal::CallErrorAsFatal(e)) => 0x0500 | e.code() as u16,
+		}
+	}
+
+	/// Reason of a code returned by `to_u16`, if any.
+	#[must_use]
+	pub const fn from_u16(code: u16) -> Option<Self> {
+		let [kind, variant] = code.to_be_bytes();
+		Some(match (kind, variant) {
+			(0x00, 0x00) => Self::StepLimitReached,
+			(0x01, 0x00) => Self::Succeed(ExitSucceed::Stopped),
+			(0x01, 0x01) => Self::Succeed(ExitSucceed::Returned),
+			(0x01, 0x02) => Self::Succeed(ExitSucceed::Suicided),
+			(0x02, _) => match ExitError::from_code(variant) {
+				Some(e) => Self::Error(e),
+				None => return None,
+			},
+			(0x03, 0x00) => Self::Revert(ExitRevert::Reverted),
+			(0x04, 0x00) => Self::Fatal(ExitFatal::NotSupported),
+			(0x04, 0x01) => Self::Fatal(ExitFatal::UnhandledInterrupt),
+			(0x04, 0x02) => Self::Fatal(ExitFatal::CodeSizeLimit),
+			(0x04, 0x03) => Self::Fatal(ExitFatal::Killed),
+			(0x04, 0x04) => Self::Fatal(ExitFatal::ConfigNotSet),
+			(0x05, _) => match ExitError::from_code(variant) {
+				Some(e) => Self::Fatal(ExitFatal::CallErrorAsFatal(e)),
+				None => return None,
+			},
+			_ => return None,
+		})
+	}
 }
 
 /// Exit succeed reason.
@@ -114,7 +177,7 @@ pub enum ExitError {
 	CallTooDeep,
 	/// Create opcode encountered collision (runtime).
 	CreateCollision,
-	/// Create init code exceeds limit (runtime).
+	/// Code deployed by a create exceeds limit (runtime).
 	CreateContractLimit,
 
 	/// An opcode accesses external information, but the request is off offset
@@ -122,7 +185,8 @@ pub enum ExitError {
 	OutOfOffset,
 	/// Execution runs out of gas (runtime).
 	OutOfGas,
-	/// Not enough fund to start the execution (runtime).
+	/// Not enough fund to start the execution, or for the value transferred
+	/// by a call or create (runtime).
 	OutOfFund,
 
 	/// PC underflowed (unused).
@@ -132,6 +196,60 @@ pub enum ExitError {
 
 	/// Indicates that a STATICCALL tried to change state
 	StaticModeViolation,
+	/// The transaction emitted more logs than allowed (runtime).
+	TooManyLogs,
+	/// A child frame returned more data than allowed (runtime).
+	ReturnDataLimit,
+	/// A jump destination was jumped back to more often than allowed.
+	LoopDetected,
+}
+
+impl ExitError {
+	/// Low byte of the `ExitReason::to_u16` code of the error.
+	const fn code(self) -> u8 {
+		match self {
+			Self::StackUnderflow => 0,
+			Self::StackOverflow => 1,
+			Self::InvalidJump => 2,
+			Self::InvalidRange => 3,
+			Self::DesignatedInvalid => 4,
+			Self::CallTooDeep => 5,
+			Self::CreateCollision => 6,
+			Self::CreateContractLimit => 7,
+			Self::OutOfOffset => 8,
+			Self::OutOfGas => 9,
+			Self::OutOfFund => 10,
+			Self::PCUnderflow => 11,
+			Self::CreateEmpty => 12,
+			Self::StaticModeViolation => 13,
+			Self::TooManyLogs => 14,
+			Self::ReturnDataLimit => 15,
+			Self::LoopDetected => 16,
+		}
+	}
+
+	const fn from_code(code: u8) -> Option<Self> {
+		Some(match code {
+			0 => Self::StackUnderflow,
+			1 => Self::StackOverflow,
+			2 => Self::InvalidJump,
+			3 => Self::InvalidRange,
+			4 => Self::DesignatedInvalid,
+			5 => Self::CallTooDeep,
+			6 => Self::CreateCollision,
+			7 => Self::CreateContractLimit,
+			8 => Self::OutOfOffset,
+			9 => Self::OutOfGas,
+			10 => Self::OutOfFund,
+			11 => Self::PCUnderflow,
+			12 => Self::CreateEmpty,
+			13 => Self::StaticModeViolation,
+			14 => Self::TooManyLogs,
+			15 => Self::ReturnDataLimit,
+			16 => Self::LoopDetected,
+			_ => return None,
+		})
+	}
 }
 
 impl From<ExitError> for ExitReason {
@@ -152,6 +270,13 @@ pub enum ExitFatal {
 	UnhandledInterrupt,
 	/// The environment explicitly set call errors as fatal error.
 	CallErrorAsFatal(ExitError),
+	/// Loaded account code exceeds the configured maximum code size (runtime).
+	CodeSizeLimit,
+	/// The host halted the runtime (runtime).
+	Killed,
+	/// A deserialized runtime was run before its configuration was restored
+	/// (runtime).
+	ConfigNotSet,
 }
 
 impl From<ExitFatal> for ExitReason {
@@ -159,3 +284,61 @@ impl From<ExitFatal> for ExitReason {
 		Self::Fatal(s)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn receipt_status_and_revert_data() {
+		let cases: [(ExitReason, u8, bool); 8] = [
+			(ExitSucceed::Stopped.into(), 1, false),
+			(ExitSucceed::Returned.into(), 1, false),
+			(ExitSucceed::Suicided.into(), 1, false),
+			(ExitRevert::Reverted.into(), 0, true),
+			(ExitError::OutOfGas.into(), 0, false),
+			(ExitError::StaticModeViolation.into(), 0, false),
+			(ExitFatal::NotSupported.into(), 0, false),
+			(ExitReason::StepLimitReached, 0, false),
+		];
+		for (reason, status, revert_bytes) in cases {
+			assert_eq!(reason.to_receipt_status(), status, "{:?}", reason);
+			assert_eq!(reason.revert_bytes(), revert_bytes, "{:?}", reason);
+		}
+	}
+
+	#[test]
+	fn numeric_codes_are_stable() {
+		let errors = [
+			ExitError::StackUnderflow, ExitError::StackOverflow, ExitError::InvalidJump, ExitError::InvalidRange,
+			ExitError::DesignatedInvalid, ExitError::CallTooDeep, ExitError::CreateCollision,
+			ExitError::CreateContractLimit, ExitError::OutOfOffset, ExitError::OutOfGas, ExitError::OutOfFund,
+			ExitError::PCUnderflow, ExitError::CreateEmpty, ExitError::StaticModeViolation, ExitError::TooManyLogs,
+			ExitError::ReturnDataLimit, ExitError::LoopDetected,
+		];
+		let mut cases = alloc::vec![
+			(ExitReason::StepLimitReached, 0x0000),
+			(ExitSucceed::Stopped.into(), 0x0100),
+			(ExitSucceed::Returned.into(), 0x0101),
+			(ExitSucceed::Suicided.into(), 0x0102),
+			(ExitRevert::Reverted.into(), 0x0300),
+			(ExitFatal::NotSupported.into(), 0x0400),
+			(ExitFatal::UnhandledInterrupt.into(), 0x0401),
+			(ExitFatal::CodeSizeLimit.into(), 0x0402),
+			(ExitFatal::Killed.into(), 0x0403),
+			(ExitFatal::ConfigNotSet.into(), 0x0404),
+		];
+		for (code, error) in (0..).zip(errors) {
+			cases.push((error.into(), 0x0200 + code));
+			cases.push((ExitFatal::CallErrorAsFatal(error).into(), 0x0500 + code));
+		}
+
+		for (reason, code) in cases {
+			assert_eq!(reason.to_u16(), code, "{:?}", reason);
+			assert_eq!(ExitReason::from_u16(code), Some(reason));
+		}
+		for code in [0x0001, 0x0103, 0x0211, 0x0301, 0x0405, 0x0511, 0x0600, 0xffff] {
+			assert_eq!(ExitReason::from_u16(code), None, "{:#06x}", code);
+		}
+	}
+}
@@ -99,3 +99,72 @@ pub fn signextend(op1: U256, op2: U256) -> U256 {
 		ret
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use crate::U256;
+	use super::{div, sdiv, rem, srem, addmod, mulmod, exp};
+
+	fn int_min() -> U256 {
+		U256::one() << 255
+	}
+
+	fn minus_one() -> U256 {
+		U256::max_value()
+	}
+
+	#[test]
+	fn test_division_by_zero_is_zero() {
+		for op1 in [U256::zero(), U256::one(), int_min(), minus_one()] {
+			assert_eq!(div(op1, U256::zero()), U256::zero());
+			assert_eq!(sdiv(op1, U256::zero()), U256::zero());
+			assert_eq!(rem(op1, U256::zero()), U256::zero());
+			assert_eq!(srem(op1, U256::zero()), U256::zero());
+			assert_eq!(addmod(op1, op1, U256::zero()), U256::zero());
+			assert_eq!(mulmod(op1, op1, U256::zero()), U256::zero());
+		}
+	}
+
+	#[test]
+	fn test_sdiv_int_min_by_minus_one() {
+		assert_eq!(sdiv(int_min(), minus_one()), int_min());
+		assert_eq!(srem(int_min(), minus_one()), U256::zero());
+	}
+
+	/// Two's complement encoding of `value`.
+	fn signed(value: i64) -> U256 {
+		if value < 0 {
+			!U256::from(value.unsigned_abs()) + U256::one()
+		} else {
+			U256::from(value)
+		}
+	}
+
+	#[test]
+	fn test_sdiv_signs() {
+		assert_eq!(sdiv(signed(7), signed(2)), signed(3));
+		assert_eq!(sdiv(signed(-7), signed(2)), signed(-3));
+		assert_eq!(sdiv(signed(7), signed(-2)), signed(-3));
+		assert_eq!(sdiv(signed(-7), signed(-2)), signed(3));
+		assert_eq!(sdiv(int_min(), signed(-1)), int_min());
+	}
+
+	#[test]
+	fn test_smod_takes_sign_of_dividend() {
+		assert_eq!(srem(signed(7), signed(3)), signed(1));
+		assert_eq!(srem(signed(-7), signed(3)), signed(-1));
+		assert_eq!(srem(signed(7), signed(-3)), signed(1));
+		assert_eq!(srem(signed(-7), signed(-3)), signed(-1));
+		assert_eq!(srem(int_min(), signed(-1)), U256::zero());
+		assert_eq!(srem(int_min(), signed(3)), signed(-2));
+	}
+
+	#[test]
+	fn test_exp_edge_cases() {
+		assert_eq!(exp(U256::zero(), U256::zero()), U256::one());
+		assert_eq!(exp(U256::from(2), U256::from(255)), int_min());
+		assert_eq!(exp(U256::from(2), U256::from(256)), U256::zero());
+		assert_eq!(exp(minus_one(), U256::from(2)), U256::one());
+		assert_eq!(exp(minus_one(), minus_one()), minus_one());
+	}
+}
@@ -159,3 +159,17 @@ macro_rules! as_usize_or_fail {
 		}
 	};
 }
+
+/// Convert to `usize`, saturating at `usize::MAX`. For offsets into input
+/// where reading past the end yields zeros.
+macro_rules! as_usize_saturated {
+	( $v:expr ) => {
+		{
+			if $v > U256::from(usize::MAX) {
+				usize::MAX
+			} else {
+				$v.as_usize()
+			}
+		}
+	};
+}
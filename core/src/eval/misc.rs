@@ -28,7 +28,7 @@ pub fn calldataload(state: &mut Machine) -> Control {
 	pop_u256!(state, index);
 	trace_op!("CallDataLoad: {}", index);
 
-	let index = as_usize_or_fail!(index);
+	let index = as_usize_saturated!(index);
 	let mut load = [0_u8; 32];
 
 	if index < state.data.len() {
@@ -52,7 +52,7 @@ pub fn calldatacopy(state: &mut Machine) -> Control {
 	trace_op!("CallDataCopy: {}", len);
 
 	let memory_offset = as_usize_or_fail!(memory_offset);
-	let data_offset = as_usize_or_fail!(data_offset);
+	let data_offset = as_usize_saturated!(data_offset);
 	let len = as_usize_or_fail!(len);
 
 	if len == 0 {
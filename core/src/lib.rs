@@ -27,6 +27,10 @@ mod primitive_types;
 mod context;
 #[cfg(feature = "tracing")]
 pub mod tracing;
+#[cfg(feature = "binary-trace")]
+pub mod binary_trace;
+#[cfg(feature = "profiling")]
+mod profiling;
 
 pub use crate::memory::Memory;
 pub use crate::stack::Stack;
@@ -35,9 +39,14 @@ pub use crate::opcode::Opcode;
 pub use crate::error::{Trap, Capture, ExitReason, ExitSucceed, ExitError, ExitRevert, ExitFatal};
 pub use crate::primitive_types::{H160, H256, U256, U512};
 pub use crate::context::{Context, CreateScheme, CallScheme, Transfer};
+#[cfg(feature = "profiling")]
+pub use crate::profiling::OpcodeTimings;
 
 use alloc::vec::Vec;
+use alloc::collections::BTreeMap;
 use crate::eval::{eval, Control};
+#[cfg(feature = "tracing")]
+use core::convert::TryFrom;
 
 #[cfg(feature = "tracing")]
 pub use crate::tracing::*;
@@ -47,7 +56,7 @@ pub use crate::tracing::*;
 #[cfg(feature = "tracing")]
 macro_rules! event {
     ($x:expr) => {
-         with(|listener| listener.event($x));
+         $crate::tracing::with(|listener| $crate::tracing::deliver(listener, $x));
     };
 }
 
@@ -78,6 +87,42 @@ pub struct Machine {
 	memory: Memory,
 	/// Stack.
 	stack: Stack,
+	/// Position of the opcode whose pre-validation made the machine exit.
+	/// That opcode has not been executed. Serialized apart, see
+	/// `resume_state`.
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(default))]
+	#[borsh_skip]
+	pre_validate_failure: Option<usize>,
+	/// Bytes decoded as another opcode. It is not serialized.
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	#[borsh_skip]
+	opcode_aliases: &'static [(u8, Opcode)],
+	/// Most backward jumps to a single destination. It is not serialized.
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	#[borsh_skip]
+	max_backward_jumps_per_dest: Option<u64>,
+	/// Backward jumps taken per destination, counted when
+	/// `max_backward_jumps_per_dest` is set, so that a resumed machine keeps
+	/// counting. Serialized apart, see `resume_state`.
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(default))]
+	#[borsh_skip]
+	backward_jumps: BTreeMap<usize, u64>,
+	/// Whether `REVERT` continues execution. It is not serialized.
+	#[cfg(feature = "debug-features")]
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	#[borsh_skip]
+	ignore_revert: bool,
+	/// Time spent per opcode. It is not serialized.
+	#[cfg(feature = "profiling")]
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	#[borsh_skip]
+	opcode_timings: OpcodeTimings,
 }
 
 impl Machine {
@@ -91,6 +136,9 @@ impl Machine {
 	pub const fn memory(&self) -> &Memory { &self.memory }
 	/// Mutable reference of machine memory.
 	pub fn memory_mut(&mut self) -> &mut Memory { &mut self.memory }
+	/// Reference of machine input data.
+	#[must_use]
+	pub fn data(&self) -> &[u8] { &self.data }
 
         /// Return a reference of the program counter.
         pub fn position(&self) -> &Result<usize, ExitReason> {
@@ -116,12 +164,150 @@ impl Machine {
 			valids,
 			memory: Memory::new(memory_limit),
 			stack: Stack::new(stack_limit),
+			pre_validate_failure: None,
+			opcode_aliases: &[],
+			max_backward_jumps_per_dest: None,
+			backward_jumps: BTreeMap::new(),
+			#[cfg(feature = "debug-features")]
+			ignore_revert: false,
+			#[cfg(feature = "profiling")]
+			opcode_timings: OpcodeTimings::default(),
 		}
 	}
 
+	/// Wall-clock time spent per opcode.
+	#[cfg(feature = "profiling")]
+	#[must_use]
+	pub const fn opcode_timings(&self) -> &OpcodeTimings {
+		&self.opcode_timings
+	}
+
+	/// Mutable reference of the time spent per opcode, for layers evaluating
+	/// trapped opcodes.
+	#[cfg(feature = "profiling")]
+	pub const fn opcode_timings_mut(&mut self) -> &mut OpcodeTimings {
+		&mut self.opcode_timings
+	}
+
+	/// Decode the bytes in `aliases` as the opcode they map to. Jump
+	/// destinations are still computed from the raw bytes.
+	pub const fn set_opcode_aliases(&mut self, aliases: &'static [(u8, Opcode)]) {
+		self.opcode_aliases = aliases;
+	}
+
+	/// Fail with `ExitError::LoopDetected` once a single destination has
+	/// been jumped back to more than `max` times, to stop likely infinite
+	/// loops before the step limit. `None` disables the check.
+	pub const fn set_max_backward_jumps_per_dest(&mut self, max: Option<u64>) {
+		self.max_backward_jumps_per_dest = max;
+	}
+
+	/// Count a jump from `position` to `dest`, returning whether it exceeds
+	/// `max_backward_jumps_per_dest`.
+	fn count_jump(&mut self, position: usize, dest: usize) -> bool {
+		match self.max_backward_jumps_per_dest {
+			Some(max) if dest < position => {
+				let count = self.backward_jumps.entry(dest).or_insert(0);
+				*count += 1;
+				*count > max
+			},
+			_ => false,
+		}
+	}
+
+	/// Emit the stack changes since the last call as `StepStackDelta`, if
+	/// the listener asked for them. Empty changes are only emitted if
+	/// `always`.
+	#[cfg(feature = "tracing")]
+	fn emit_stack_delta(&mut self, always: bool) {
+		if !tracing::stack_deltas() {
+			return;
+		}
+		let (mut popped, pushed) = self.stack.take_delta();
+		if !always && popped == 0 && pushed.is_empty() {
+			return;
+		}
+		// Only a listener enabled mid-run can see more than 255 pops.
+		while popped > usize::from(u8::MAX) {
+			event!(Event::StepStackDelta(StepStackDeltaTrace { popped: u8::MAX, pushed: &[] }));
+			popped -= usize::from(u8::MAX);
+		}
+		let popped = u8::try_from(popped).unwrap_or(u8::MAX);
+		event!(Event::StepStackDelta(StepStackDeltaTrace { popped, pushed }));
+	}
+
+	/// Make `REVERT` continue with the next opcode instead of exiting. For
+	/// post-mortem debugging only: nothing is rolled back, so the execution
+	/// no longer matches the chain.
+	#[cfg(feature = "debug-features")]
+	pub const fn set_ignore_revert(&mut self, ignore_revert: bool) {
+		self.ignore_revert = ignore_revert;
+	}
+
+	/// Instructions of the code as `(position, opcode, immediate)`, with the
+	/// data of every `PUSH`, truncated at the end of the code, as immediate.
+	/// Opcodes are decoded after aliasing, `PUSH` data is skipped based on
+	/// the raw bytes like `Valids::compute` does.
+	#[must_use]
+	pub fn disassemble(&self) -> Vec<(usize, Opcode, Option<Vec<u8>>)> {
+		let mut instructions = Vec::new();
+
+		let mut i = 0;
+		while i < self.code.len() {
+			let byte = self.code[i];
+			let immediate = if let 0x60..=0x7f = byte {
+				let end = core::cmp::min(i + 1 + usize::from(byte - 0x5f), self.code.len());
+				Some(self.code[(i + 1)..end].to_vec())
+			} else {
+				None
+			};
+			let len = immediate.as_ref().map_or(0, Vec::len);
+			instructions.push((i, self.decode(byte), immediate));
+			i += 1 + len;
+		}
+
+		instructions
+	}
+
+	/// Opcode of `byte`, after aliasing.
+	fn decode(&self, byte: u8) -> Opcode {
+		self.opcode_aliases.iter()
+			.find(|(alias, _)| *alias == byte)
+			.map_or(Opcode(byte), |(_, opcode)| *opcode)
+	}
+
 	/// Explicit exit of the machine. Further step will return error.
 	pub fn exit(&mut self, reason: ExitReason) {
 		self.position = Err(reason);
+		self.pre_validate_failure = None;
+	}
+
+	/// Resume a machine that exited because pre-validation failed, so that
+	/// the rejected opcode is validated again on the next step. Returns
+	/// `false`, leaving the machine unchanged, if it did not exit that way.
+	pub const fn resume(&mut self) -> bool {
+		match self.pre_validate_failure.take() {
+			Some(position) => {
+				self.position = Ok(position);
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// State needed to resume the machine that its borsh layout leaves out,
+	/// so that the layout stays the one of machines serialized before it
+	/// existed: the position whose pre-validation failed and the backward
+	/// jumps taken. Serialized runtimes keep it in their versioned layout.
+	#[must_use]
+	pub const fn resume_state(&self) -> (Option<usize>, &BTreeMap<usize, u64>) {
+		(self.pre_validate_failure, &self.backward_jumps)
+	}
+
+	/// Restore the state returned by `resume_state` after deserializing.
+	pub fn set_resume_state(&mut self, pre_validate_failure: Option<usize>, backward_jumps: BTreeMap<usize, u64>) {
+		self.pre_validate_failure = pre_validate_failure;
+		self.backward_jumps = backward_jumps;
 	}
 
 	/// Inspect the machine's next opcode and current stack.
@@ -131,7 +317,7 @@ impl Machine {
 			Ok(position) => position,
 			Err(_) => return None,
 		};
-		self.code.get(position).map(|v| (Opcode(*v), &self.stack))
+		self.code.get(position).map(|v| (self.decode(*v), &self.stack))
 	}
 
 	/// Gets return value len by `return_range`
@@ -164,13 +350,17 @@ impl Machine {
 			};
 
 			let opcode = match self.code.get(position) {
-				Some(opcode) => Opcode(*opcode),
+				Some(opcode) => self.decode(*opcode),
 				None => {
 					self.position = Err(ExitReason::Succeed(ExitSucceed::Stopped));
 					return (step, Capture::Exit(ExitReason::Succeed(ExitSucceed::Stopped)));
 				}
 			};
 
+			// Changes made between steps, such as the result of a call.
+			#[cfg(feature = "tracing")]
+			self.emit_stack_delta(false);
+
 			event!(Event::Step(
 				StepTrace {
 					context: _context,
@@ -184,10 +374,28 @@ impl Machine {
 			if let Err(error) = pre_validate(opcode, &self.stack()) {
 				let reason = ExitReason::from(error);
 				self.exit(reason);
+				self.pre_validate_failure = Some(position);
 				return (step, Capture::Exit(reason));
 			}
 
-			let result = match eval(self, opcode, position) {
+			#[cfg(feature = "profiling")]
+			let started = std::time::Instant::now();
+			let control = eval(self, opcode, position);
+			#[cfg(feature = "profiling")]
+			self.opcode_timings.record(opcode, started.elapsed());
+			#[cfg(feature = "debug-features")]
+			let control = match control {
+				Control::Exit(ExitReason::Revert(_)) if self.ignore_revert => Control::Continue(1),
+				control => control,
+			};
+			let control = match control {
+				Control::Jump(dest) if self.count_jump(position, dest) => {
+					Control::Exit(ExitError::LoopDetected.into())
+				},
+				control => control,
+			};
+
+			let result = match control {
 				Control::Continue(p) => {
 					self.position = Ok(position + p);
 					Ok(())
@@ -206,6 +414,11 @@ impl Machine {
 				},
 			};
 
+			#[cfg(all(debug_assertions, feature = "debug-invariants"))]
+			self.check_invariants(opcode, position);
+
+			#[cfg(feature = "tracing")]
+			self.emit_stack_delta(true);
 			event!(Event::StepResult (StepResultTrace{
 				result: &result,
 				return_value: &self.return_value(),
@@ -221,4 +434,42 @@ impl Machine {
 		(max_steps, Capture::Exit(ExitReason::StepLimitReached))
 	}
 
+	/// Validate internal machine invariants after `opcode` at `position`
+	/// has been evaluated. Panics with a description of the violation.
+	#[cfg(all(debug_assertions, feature = "debug-invariants"))]
+	fn check_invariants(&self, opcode: Opcode, position: usize) {
+		let context = || alloc::format!("after {opcode:?} at position {position}");
+
+		if let Ok(next) = self.position {
+			// A truncated PUSH at the end of code may step up to 32 bytes past it.
+			assert!(
+				next <= self.code.len() + 32,
+				"machine invariant: position {} out of code bounds ({} bytes) {}",
+				next, self.code.len(), context(),
+			);
+			let jumped = (opcode == Opcode::JUMP || opcode == Opcode::JUMPI) && next != position + 1;
+			assert!(
+				!jumped || self.valids.is_valid(next),
+				"machine invariant: jumped to invalid destination {} {}",
+				next, context(),
+			);
+		}
+
+		assert!(
+			self.stack.len() <= self.stack.limit(),
+			"machine invariant: stack length {} exceeds limit {} {}",
+			self.stack.len(), self.stack.limit(), context(),
+		);
+		assert!(
+			self.memory.len() <= self.memory.limit(),
+			"machine invariant: memory length {} exceeds limit {} {}",
+			self.memory.len(), self.memory.limit(), context(),
+		);
+		assert!(
+			self.memory.effective_len() % 32 == 0 && self.memory.len() <= self.memory.effective_len(),
+			"machine invariant: memory length {} inconsistent with effective length {} {}",
+			self.memory.len(), self.memory.effective_len(), context(),
+		);
+	}
+
 }
@@ -38,7 +38,9 @@ impl Memory {
 		self.data.len()
 	}
 
-	/// Get the effective length.
+	/// Get the effective length: the highest end of any range accessed,
+	/// rounded up to a word, from which memory gas is computed. It may exceed
+	/// `len`, which only covers bytes actually written.
 	#[must_use]
 	pub const fn effective_len(&self) -> usize {
 		self.effective_len
@@ -56,17 +58,19 @@ impl Memory {
 
 	/// Resize the memory, making it cover the memory region of `offset..(offset
 	/// + len)`, with 32 bytes as the step. If the length is zero, this function
-	/// does nothing.
-	pub fn resize_offset(&mut self, offset: usize, len: usize) -> Result<(), ExitError> {
+	/// does nothing. Returns the number of 32-byte words added.
+	pub fn resize_offset(&mut self, offset: usize, len: usize) -> Result<usize, ExitError> {
 		if len == 0 {
-			return Ok(())
+			return Ok(0)
 		}
 
 		offset.checked_add(len).map_or(Err(ExitError::InvalidRange), |end| self.resize_end(end))
 	}
 
 	/// Resize the memory, making it cover to `end`, with 32 bytes as the step.
-	pub fn resize_end(&mut self, end: usize) -> Result<(), ExitError> {
+	/// Returns the number of 32-byte words added, zero if the memory already
+	/// covers `end`.
+	pub fn resize_end(&mut self, end: usize) -> Result<usize, ExitError> {
 		let end = {
 			let modulo = end % 32;
 			if modulo == 0 {
@@ -81,8 +85,9 @@ impl Memory {
 			}
 		};
 
+		let grown = end.saturating_sub(self.effective_len) / 32;
 		self.effective_len = max(self.effective_len, end);
-		Ok(())
+		Ok(grown)
 	}
 
 	/// Get memory region at given offset.
@@ -155,3 +160,33 @@ impl Memory {
 		self.set(memory_offset, data_by_offset, Some(len))
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn resize_reports_words_grown() {
+		let mut memory = Memory::new(usize::MAX);
+		assert_eq!(memory.resize_offset(0, 1), Ok(1));
+		assert_eq!(memory.resize_offset(32, 64), Ok(2));
+		assert_eq!(memory.effective_len(), 96);
+	}
+
+	#[test]
+	fn resize_within_memory_grows_nothing() {
+		let mut memory = Memory::new(usize::MAX);
+		assert_eq!(memory.resize_offset(0, 64), Ok(2));
+		assert_eq!(memory.resize_offset(10, 40), Ok(0));
+		assert_eq!(memory.resize_offset(1000, 0), Ok(0));
+		assert_eq!(memory.effective_len(), 64);
+	}
+
+	#[test]
+	fn resize_to_smaller_end_is_a_no_op() {
+		let mut memory = Memory::new(usize::MAX);
+		assert_eq!(memory.resize_end(128), Ok(4));
+		assert_eq!(memory.resize_end(32), Ok(0));
+		assert_eq!(memory.effective_len(), 128);
+	}
+}
@@ -1,6 +1,7 @@
 #![allow(clippy::use_self)]
 /// Opcode enum. One-to-one corresponding to an `u8` value.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct Opcode(pub u8);
 
 // Core opcodes.
@@ -181,6 +182,8 @@ impl Opcode {
 	pub const SELFBALANCE: Opcode = Opcode(0x47);
 	/// `BASEFEE`
 	pub const BASEFEE: Opcode = Opcode(0x48);
+	/// `BLOBBASEFEE`
+	pub const BLOBBASEFEE: Opcode = Opcode(0x4a);
 	/// `ORIGIN`
 	pub const ORIGIN: Opcode = Opcode(0x32);
 	/// `CALLER`
@@ -254,4 +257,9 @@ impl Opcode {
 	pub const fn as_usize(self) -> usize {
 		self.0 as usize
 	}
+
+	/// Iterate over all 256 opcodes, in byte order.
+	pub fn all() -> impl Iterator<Item = Opcode> {
+		(0..=u8::MAX).map(Opcode)
+	}
 }
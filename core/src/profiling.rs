@@ -0,0 +1,33 @@
+use core::convert::TryFrom;
+use std::time::Duration;
+use crate::Opcode;
+
+/// Wall-clock nanoseconds spent per opcode.
+#[derive(Clone, Debug)]
+pub struct OpcodeTimings([u64; 256]);
+
+impl Default for OpcodeTimings {
+	fn default() -> Self {
+		Self([0; 256])
+	}
+}
+
+impl OpcodeTimings {
+	/// Nanoseconds accumulated per opcode, indexed by opcode byte.
+	#[must_use]
+	pub const fn as_array(&self) -> &[u64; 256] {
+		&self.0
+	}
+
+	/// Add `elapsed` to the time spent in `opcode`, saturating.
+	pub fn record(&mut self, opcode: Opcode, elapsed: Duration) {
+		let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+		let total = &mut self.0[opcode.as_usize()];
+		*total = total.saturating_add(nanos);
+	}
+
+	/// Zero every timing.
+	pub const fn reset(&mut self) {
+		self.0 = [0; 256];
+	}
+}
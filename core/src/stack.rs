@@ -57,6 +57,20 @@ pub struct Stack {
 	#[cfg_attr(feature = "with-serde", serde(with="serde_vec_u256"))]
 	data: Vec<U256>,
 	limit: usize,
+	#[cfg(feature = "tracing")]
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	#[borsh_skip]
+	delta: DeltaMark,
+}
+
+/// Stack length at the last `take_delta`, and the lowest index modified
+/// since then.
+#[cfg(feature = "tracing")]
+#[derive(Clone, Copy, Debug, Default)]
+struct DeltaMark {
+	len: usize,
+	touched: usize,
 }
 
 impl Stack {
@@ -66,6 +80,8 @@ impl Stack {
 		Self {
 			data: Vec::new(),
 			limit,
+			#[cfg(feature = "tracing")]
+			delta: DeltaMark { len: 0, touched: 0 },
 		}
 	}
 
@@ -75,6 +91,12 @@ impl Stack {
 		self.limit
 	}
 
+	/// Stack data, from bottom to top.
+	#[must_use]
+	pub fn data(&self) -> &[U256] {
+		&self.data
+	}
+
 	/// Stack length.
 	#[must_use]
 	#[allow(clippy::len_without_is_empty)]
@@ -85,13 +107,10 @@ impl Stack {
 	/// Pop a value from the stack. If the stack is already empty, returns the
 	/// `StackUnderflow` error.
 	pub fn pop(&mut self) -> Result<H256, ExitError> {
-		self.data.pop()
-			.map(|d| {
-				let mut value = H256::default();
-				d.into_big_endian_fast(&mut value[..]);
-				value
-			})
-			.ok_or(ExitError::StackUnderflow)
+		let d = self.pop_u256()?;
+		let mut value = H256::default();
+		d.into_big_endian_fast(&mut value[..]);
+		Ok(value)
 	}
 
 	/// Push a new value into the stack. If it will exceed the stack limit,
@@ -107,7 +126,10 @@ impl Stack {
 	/// Pop a value from the stack. If the stack is already empty, returns the
 	/// `StackUnderflow` error.
 	pub fn pop_u256(&mut self) -> Result<U256, ExitError> {
-		self.data.pop().ok_or(ExitError::StackUnderflow)
+		let value = self.data.pop().ok_or(ExitError::StackUnderflow)?;
+		#[cfg(feature = "tracing")]
+		self.touch(self.data.len());
+		Ok(value)
 	}
 
 	/// Push a new value into the stack. If it will exceed the stack limit,
@@ -136,8 +158,10 @@ impl Stack {
 	/// `StackError::Underflow` is returned.
 	pub fn set(&mut self, no_from_top: usize, val: U256) -> Result<(), ExitError> {
 		if self.data.len() > no_from_top {
-			let len = self.data.len();
-			self.data[len - no_from_top - 1] = val;
+			let index = self.data.len() - no_from_top - 1;
+			self.data[index] = val;
+			#[cfg(feature = "tracing")]
+			self.touch(index);
 			Ok(())
 		} else {
 			Err(ExitError::StackUnderflow)
@@ -165,7 +189,26 @@ impl Stack {
 		let b = len - 1;
 
 		self.data.swap(a, b);
+		#[cfg(feature = "tracing")]
+		self.touch(a);
 
 		Ok(())
 	}
+
+	/// Record that the item at `index` and all above it may have changed.
+	#[cfg(feature = "tracing")]
+	fn touch(&mut self, index: usize) {
+		self.delta.touched = self.delta.touched.min(index);
+	}
+
+	/// Changes since the previous call, as the number of items popped and
+	/// the items pushed afterwards. Items modified in place count as popped
+	/// and pushed again. A new or deserialized stack starts from empty.
+	#[cfg(feature = "tracing")]
+	pub(crate) fn take_delta(&mut self) -> (usize, &[U256]) {
+		let popped = self.delta.len - self.delta.touched;
+		let touched = self.delta.touched;
+		self.delta = DeltaMark { len: self.data.len(), touched: self.data.len() };
+		(popped, &self.data[touched..])
+	}
 }
@@ -3,8 +3,41 @@ use alloc::vec::Vec;
 
 environmental::environmental!(listener: dyn EventListener + 'static);
 
+/// Event sequence numbers, in a module of their own as `environmental`
+/// allows one global per module.
+mod sequence {
+    environmental::environmental!(counter: u64);
+
+    pub fn using<R, F: FnOnce() -> R>(f: F) -> R {
+        counter::using(&mut 0, f)
+    }
+
+    pub fn next() -> u64 {
+        counter::with(|seq| {
+            let current = *seq;
+            *seq += 1;
+            current
+        }).unwrap_or_default()
+    }
+}
+
 pub trait EventListener {
     fn event(&mut self, event: Event);
+
+    /// Receive `event` together with its sequence number: the number of
+    /// events emitted before it within the enclosing `using` call. Forwards
+    /// to `event` by default.
+    fn sequenced_event(&mut self, seq: u64, event: Event) {
+        let _ = seq;
+        self.event(event);
+    }
+
+    /// Whether to emit a `StepStackDelta` before every `StepResult`, and
+    /// before a `Step` whose stack was changed since the previous step, so
+    /// that the stack can be traced without recording the full snapshots.
+    fn stack_deltas(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug,  Clone)]
@@ -24,6 +57,14 @@ pub struct StepResultTrace<'a>{
     pub memory: &'a Memory,
 }
 
+/// Stack changes since the previous delta of the same machine: `popped`
+/// items were removed, then `pushed` were added, from bottom to top.
+#[derive(Debug,  Clone)]
+pub struct StepStackDeltaTrace<'a>{
+    pub popped: u8,
+    pub pushed: &'a [U256],
+}
+
 #[derive(Debug,  Clone)]
 pub struct SLoadTrace{
     pub address: H160,
@@ -38,13 +79,61 @@ pub struct SStoreTrace {
     pub value: U256
 }
 
+/// Net storage changes made by a frame, fired when it exits.
+///
+/// Changes are `(index, old, new)` sorted by index, and empty for frames
+/// that did not succeed. Changes made by child frames are reported by their
+/// own events.
+#[derive(Debug,  Clone)]
+pub struct StorageDiffTrace {
+    pub address: H160,
+    pub changes: Vec<(U256, U256, U256)>,
+}
+
+/// Nonce increment of a creating account.
+#[derive(Debug,  Clone)]
+pub struct IncrementNonceTrace {
+    pub address: H160,
+}
+
+/// Gas summary of a transaction, fired once its top-level frame has exited.
+#[derive(Debug,  Clone)]
+pub struct TransactionResultTrace {
+    pub gas_used: u64,
+    pub gas_refunded: u64,
+    pub gas_used_after_refund: u64,
+    pub exit_reason: ExitReason,
+}
+
+/// Gas used by a frame, fired when it exits. `gas_used` includes
+/// `gas_used_children`, the gas used by the calls and creates it made.
+#[derive(Debug,  Clone)]
+pub struct FrameGasTrace {
+    pub depth: usize,
+    pub gas_used: u64,
+    pub gas_used_children: u64,
+}
+
+/// Event of a chain extension, identified by a `tag` of its choosing.
+#[derive(Debug,  Clone)]
+pub struct CustomTrace<'a> {
+    pub tag: u32,
+    pub data: &'a [u8],
+}
+
 /// Trace event
 #[derive(Debug,  Clone)]
 pub enum Event<'a>{
     Step(StepTrace<'a>) ,
     StepResult(StepResultTrace<'a>),
+    StepStackDelta(StepStackDeltaTrace<'a>),
     SLoad(SLoadTrace),
     SStore(SStoreTrace),
+    StorageDiff(StorageDiffTrace),
+    TransactionResult(TransactionResultTrace),
+    IncrementNonce(IncrementNonceTrace),
+    FrameGas(FrameGasTrace),
+    Custom(CustomTrace<'a>),
 }
 
 
@@ -52,6 +141,106 @@ pub fn with<F: FnOnce(&mut (dyn EventListener + 'static))>(f: F) {
     listener::with(f);
 }
 
+/// Whether the current listener asked for `StepStackDelta` events.
+#[must_use]
+pub fn stack_deltas() -> bool {
+    let mut enabled = false;
+    listener::with(|listener| enabled = listener.stack_deltas());
+    enabled
+}
+
+/// Deliver `event` to `listener`, assigning it the next sequence number.
+pub fn deliver(listener: &mut (dyn EventListener + 'static), event: Event) {
+    listener.sequenced_event(sequence::next(), event);
+}
+
+/// Run `f` with `new` receiving its events. Sequence numbers start at zero
+/// for every call, so they order the events of one transaction when each is
+/// traced with its own `using`.
 pub fn using<R, F: FnOnce() -> R>(new: &mut (dyn EventListener + 'static), f: F) -> R {
-    listener::using(new, f)
+    sequence::using(|| listener::using(new, f))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+    use crate::{H160, U256, Context, Machine, Valids};
+    use super::{Event, EventListener, using};
+
+    /// Records the sequence number of every event.
+    #[derive(Default)]
+    struct Sequence(Vec<u64>);
+
+    impl EventListener for Sequence {
+        fn event(&mut self, _event: Event) {
+            unreachable!("events are delivered with their sequence number");
+        }
+
+        fn sequenced_event(&mut self, seq: u64, _event: Event) {
+            self.0.push(seq);
+        }
+    }
+
+    /// Replays the stack deltas, checking them against every full snapshot.
+    #[derive(Default)]
+    struct Replay {
+        stack: Vec<U256>,
+        steps: usize,
+    }
+
+    impl EventListener for Replay {
+        fn event(&mut self, event: Event) {
+            match event {
+                Event::StepStackDelta(delta) => {
+                    let len = self.stack.len() - usize::from(delta.popped);
+                    self.stack.truncate(len);
+                    self.stack.extend_from_slice(delta.pushed);
+                },
+                Event::Step(step) => {
+                    assert_eq!(self.stack, step.stack.data());
+                    self.steps += 1;
+                },
+                Event::StepResult(result) => assert_eq!(self.stack, result.stack.data()),
+                _ => {},
+            }
+        }
+
+        fn stack_deltas(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_stack_deltas_replay_snapshots() {
+        // PUSH1 1, PUSH1 2, PUSH1 3, SWAP2, DUP3, ADD, NOT, POP, ISZERO, DUP1, MSTORE, PUSH1 7
+        let code = vec![
+            0x60, 0x01, 0x60, 0x02, 0x60, 0x03, 0x91, 0x82, 0x01, 0x19, 0x50, 0x15, 0x80, 0x52, 0x60, 0x07,
+        ];
+        let valids = Valids::compute(&code);
+        let mut machine = Machine::new(code, valids, Vec::new(), 1024, 10000);
+        let context = Context { address: H160::default(), caller: H160::default(), apparent_value: U256::zero() };
+
+        let mut replay = Replay::default();
+        using(&mut replay, || machine.run(u64::MAX, |_, _| Ok(()), &context));
+        assert_eq!(replay.steps, 12);
+        // The delta of the last opcode is emitted too.
+        assert_eq!(replay.stack, machine.stack().data());
+        assert_eq!(replay.stack, vec![U256::from(3), U256::from(7)]);
+    }
+
+    #[test]
+    fn test_sequence_numbers_restart_per_using() {
+        // PUSH1 1, POP, STOP
+        let code = vec![0x60, 0x01, 0x50, 0x00];
+        let context = Context { address: H160::default(), caller: H160::default(), apparent_value: U256::zero() };
+
+        for _ in 0..2 {
+            let valids = Valids::compute(&code);
+            let mut machine = Machine::new(code.clone(), valids, Vec::new(), 1024, 10000);
+            let mut sequence = Sequence::default();
+            using(&mut sequence, || machine.run(u64::MAX, |_, _| Ok(()), &context));
+            assert!(!sequence.0.is_empty());
+            assert!(sequence.0.iter().copied().eq(0..sequence.0.len() as u64));
+        }
+    }
 }
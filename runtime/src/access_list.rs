@@ -0,0 +1,285 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::vec::Vec;
+use crate::{Capture, Stack, ExitError, ExitFatal, Opcode, Machine, ExitReason, Handler, BlockEnv, H160, H256, U256};
+use evm_core::{Context, CreateScheme, Transfer};
+
+/// Handler decorator recording every account and storage slot accessed
+/// through the inner handler, as needed by `eth_createAccessList`.
+///
+/// Accesses are recorded whether or not the inner handler does EIP-2929
+/// accounting, and are kept when the accessing frame reverts. Nested frames
+/// are only recorded if they also run through the recorder, so handlers
+/// executing them synchronously should resolve interrupts instead.
+pub struct AccessListRecorder<H> {
+	inner: H,
+	accessed: RefCell<BTreeMap<H160, BTreeSet<H256>>>,
+}
+
+impl<H: Handler> AccessListRecorder<H> {
+	/// Wrap `inner` with nothing recorded.
+	pub const fn new(inner: H) -> Self {
+		Self {
+			inner,
+			accessed: RefCell::new(BTreeMap::new()),
+		}
+	}
+
+	/// Get a reference to the inner handler.
+	pub const fn inner(&self) -> &H {
+		&self.inner
+	}
+
+	/// Unwrap the inner handler.
+	pub fn into_inner(self) -> H {
+		self.inner
+	}
+
+	/// Accessed accounts with their accessed storage slots, both in ascending
+	/// order whatever order they were accessed in, so the same accesses always
+	/// give the same list. The sender, the recipient and precompiles are included if they
+	/// were accessed; `eth_createAccessList` usually leaves them out.
+	pub fn access_list(&self) -> Vec<(H160, Vec<H256>)> {
+		self.accessed.borrow().iter()
+			.map(|(address, slots)| (*address, slots.iter().copied().collect()))
+			.collect()
+	}
+
+	fn record(&self, address: H160) {
+		self.accessed.borrow_mut().entry(address).or_default();
+	}
+
+	fn record_slot(&self, address: H160, index: U256) {
+		self.accessed.borrow_mut().entry(address).or_default().insert(H256::from(index));
+	}
+}
+
+impl<H: Handler> Handler for AccessListRecorder<H> {
+	type CreateInterrupt = H::CreateInterrupt;
+	type CreateFeedback = H::CreateFeedback;
+	type CallInterrupt = H::CallInterrupt;
+	type CallFeedback = H::CallFeedback;
+
+	fn keccak256_h256(&self, data: &[u8]) -> H256 { self.inner.keccak256_h256(data) }
+	fn try_keccak256_h256(&self, data: &[u8]) -> Result<H256, ExitFatal> { self.inner.try_keccak256_h256(data) }
+
+	fn nonce(&self, address: H160) -> U256 {
+		self.record(address);
+		self.inner.nonce(address)
+	}
+
+	fn balance(&self, address: H160) -> U256 {
+		self.record(address);
+		self.inner.balance(address)
+	}
+
+	fn code_size(&self, address: H160) -> U256 {
+		self.record(address);
+		self.inner.code_size(address)
+	}
+
+	fn code_hash(&self, address: H160) -> H256 {
+		self.record(address);
+		self.inner.code_hash(address)
+	}
+
+	fn code(&self, address: H160) -> Vec<u8> {
+		self.record(address);
+		self.inner.code(address)
+	}
+
+	fn has_code(&self, address: H160) -> bool {
+		self.record(address);
+		self.inner.has_code(address)
+	}
+
+	fn prefetch(&self, addresses: &[H160], slots: &[(H160, H256)]) {
+		// Not an access.
+		self.inner.prefetch(addresses, slots);
+	}
+
+	fn account_exists(&self, address: H160) -> bool {
+		self.record(address);
+		self.inner.account_exists(address)
+	}
+
+	fn account_exists_for_create(&self, address: H160) -> bool { self.inner.account_exists_for_create(address) }
+	fn is_precompile(&self, address: H160) -> bool { self.inner.is_precompile(address) }
+	fn precompile_gas(&self, address: H160, input: &[u8]) -> Result<u64, ExitError> {
+		self.inner.precompile_gas(address, input)
+	}
+
+	fn valids(&self, address: H160) -> Vec<u8> {
+		self.record(address);
+		self.inner.valids(address)
+	}
+
+	fn delegated_code(&self, address: H160) -> Option<H160> {
+		self.record(address);
+		let delegate = self.inner.delegated_code(address);
+		if let Some(delegate) = delegate {
+			self.record(delegate);
+		}
+		delegate
+	}
+
+	fn storage(&self, address: H160, index: U256) -> U256 {
+		self.record_slot(address, index);
+		self.inner.storage(address, index)
+	}
+
+	fn storage_batch(&self, address: H160, indices: &[U256]) -> Vec<U256> {
+		// Not recorded: the runtime only batches the reads of `prefetch`.
+		self.inner.storage_batch(address, indices)
+	}
+
+	fn gas_left(&self) -> U256 { self.inner.gas_left() }
+	fn gas_price(&self) -> U256 { self.inner.gas_price() }
+	fn effective_gas_price(&self) -> U256 { self.inner.effective_gas_price() }
+	fn origin(&self) -> H160 { self.inner.origin() }
+	fn block_hash(&self, number: U256) -> H256 { self.inner.block_hash(number) }
+	fn block_number(&self) -> U256 { self.inner.block_number() }
+	fn block_number_u64(&self) -> u64 { self.inner.block_number_u64() }
+	fn block_coinbase(&self) -> H160 { self.inner.block_coinbase() }
+	fn block_timestamp(&self) -> U256 { self.inner.block_timestamp() }
+	fn block_difficulty(&self) -> U256 { self.inner.block_difficulty() }
+	fn block_gas_limit(&self) -> U256 { self.inner.block_gas_limit() }
+	fn block_gas_limit_u64(&self) -> u64 { self.inner.block_gas_limit_u64() }
+	fn chain_id(&self) -> U256 { self.inner.chain_id() }
+	fn block_base_fee_per_gas(&self) -> U256 { self.inner.block_base_fee_per_gas() }
+	fn block_env(&self) -> BlockEnv { self.inner.block_env() }
+	fn excess_blob_gas(&self) -> u64 { self.inner.excess_blob_gas() }
+	fn blob_base_fee(&self) -> U256 { self.inner.blob_base_fee() }
+
+	fn set_storage(&mut self, address: H160, index: U256, value: U256) -> Result<(), ExitError> {
+		self.record_slot(address, index);
+		self.inner.set_storage(address, index, value)
+	}
+
+	fn replace_storage(&mut self, address: H160, index: U256, value: U256) -> Result<U256, ExitError> {
+		self.record_slot(address, index);
+		self.inner.replace_storage(address, index, value)
+	}
+
+	fn log(&mut self, address: H160, topics: Vec<H256>, data: Vec<u8>) -> Result<(), ExitError> {
+		self.inner.log(address, topics, data)
+	}
+
+	fn log_count(&self) -> usize {
+		self.inner.log_count()
+	}
+
+	#[cfg(feature = "tracing")]
+	fn custom_event(&self, tag: u32, data: &[u8]) {
+		self.inner.custom_event(tag, data);
+	}
+
+	fn mark_delete(&mut self, address: H160, target: H160) -> Result<(), ExitError> {
+		self.record(target);
+		self.inner.mark_delete(address, target)
+	}
+
+	fn selfdestruct(&mut self, address: H160, beneficiary: H160, balance: U256) -> Result<(), ExitError> {
+		self.record(beneficiary);
+		self.inner.selfdestruct(address, beneficiary, balance)
+	}
+
+	fn is_selfdestructed(&self, address: H160) -> bool {
+		self.inner.is_selfdestructed(address)
+	}
+
+	fn is_cold_account(&self, address: H160) -> bool {
+		self.inner.is_cold_account(address)
+	}
+
+	fn warm_account(&mut self, address: H160) {
+		// Created addresses are only known once warmed.
+		self.record(address);
+		self.inner.warm_account(address);
+	}
+
+	fn inc_nonce(&mut self, address: H160) -> Result<bool, ExitError> {
+		self.inner.inc_nonce(address)
+	}
+
+	fn create(
+		&mut self,
+		caller: H160,
+		scheme: CreateScheme,
+		address: H160,
+		value: U256,
+		init_code: Vec<u8>,
+		target_gas: Option<u64>,
+	) -> Capture<(ExitReason, Option<H160>, Vec<u8>), Self::CreateInterrupt> {
+		self.inner.create(caller, scheme, address, value, init_code, target_gas)
+	}
+
+	fn create_sync(
+		&mut self,
+		interrupt: Self::CreateInterrupt,
+	) -> Capture<(ExitReason, Option<H160>, Vec<u8>), Self::CreateInterrupt> {
+		self.inner.create_sync(interrupt)
+	}
+
+	fn create_depth(&self) -> usize {
+		self.inner.create_depth()
+	}
+
+	fn create_feedback(&mut self, feedback: Self::CreateFeedback) -> Result<(), ExitError> {
+		self.inner.create_feedback(feedback)
+	}
+
+	fn transfer(&mut self, transfer: &Transfer) -> Result<(), ExitError> {
+		self.inner.transfer(transfer)
+	}
+
+	fn call(
+		&mut self,
+		code_address: H160,
+		transfer: Option<Transfer>,
+		input: Vec<u8>,
+		target_gas: Option<u64>,
+		is_static: bool,
+		context: Context,
+	) -> Capture<(ExitReason, Vec<u8>), Self::CallInterrupt> {
+		self.record(code_address);
+		self.record(context.address);
+		self.inner.call(code_address, transfer, input, target_gas, is_static, context)
+	}
+
+	fn call_sync(&mut self, interrupt: Self::CallInterrupt) -> Capture<(ExitReason, Vec<u8>), Self::CallInterrupt> {
+		self.inner.call_sync(interrupt)
+	}
+
+	fn call_feedback(&mut self, feedback: Self::CallFeedback) -> Result<(), ExitError> {
+		self.inner.call_feedback(feedback)
+	}
+
+	fn enter_frame(&mut self) {
+		self.inner.enter_frame();
+	}
+
+	fn exit_frame(&mut self, reverted: bool) {
+		self.inner.exit_frame(reverted);
+	}
+
+	fn pre_validate(&mut self, context: &Context, opcode: Opcode, stack: &Stack) -> Result<(), ExitError> {
+		self.inner.pre_validate(context, opcode, stack)
+	}
+
+	fn record_cost(&mut self, cost: u64) -> Result<(), ExitError> {
+		self.inner.record_cost(cost)
+	}
+
+	fn record_refund(&mut self, refund: i64) -> Result<(), ExitError> {
+		self.inner.record_refund(refund)
+	}
+
+	fn preview_gas_cost(&self, context: &Context, opcode: Opcode, machine: &Machine) -> Option<u64> {
+		self.inner.preview_gas_cost(context, opcode, machine)
+	}
+
+	fn other(&mut self, opcode: Opcode, machine: &mut Machine) -> Result<(), ExitError> {
+		self.inner.other(opcode, machine)
+	}
+}
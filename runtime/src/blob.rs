@@ -0,0 +1,88 @@
+use crate::U256;
+
+/// Minimum blob base fee (EIP-4844).
+pub const MIN_BLOB_BASE_FEE: u64 = 1;
+/// Controls the maximum rate of change of the blob base fee (EIP-4844).
+pub const BLOB_BASE_FEE_UPDATE_FRACTION: u64 = 3_338_477;
+
+/// Approximate `factor * e ** (numerator / denominator)` using the Taylor
+/// expansion from EIP-4844. Saturates to `U256::max_value()` on overflow,
+/// and for a zero `denominator` unless `factor` is zero.
+#[must_use]
+pub fn fake_exponential(factor: u64, numerator: u64, denominator: u64) -> U256 {
+	if denominator == 0 {
+		return if factor == 0 { U256::zero() } else { U256::max_value() };
+	}
+
+	let numerator = U256::from(numerator);
+	let denominator = U256::from(denominator);
+
+	let mut i = U256::one();
+	let mut output = U256::zero();
+	let mut accum = U256::from(factor) * denominator;
+	while !accum.is_zero() {
+		output = match output.checked_add(accum) {
+			Some(output) => output,
+			None => return U256::max_value(),
+		};
+		accum = match accum.checked_mul(numerator) {
+			Some(accum) => accum / (denominator * i),
+			None => return U256::max_value(),
+		};
+		i += U256::one();
+	}
+
+	output / denominator
+}
+
+/// Blob base fee for the given block excess blob gas (EIP-4844).
+#[must_use]
+pub fn calc_blob_base_fee(excess_blob_gas: u64) -> U256 {
+	fake_exponential(MIN_BLOB_BASE_FEE, excess_blob_gas, BLOB_BASE_FEE_UPDATE_FRACTION)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{calc_blob_base_fee, fake_exponential};
+	use crate::U256;
+
+	#[test]
+	fn test_fake_exponential() {
+		let vectors: [(u64, u64, u64, u64); 15] = [
+			(1, 0, 1, 1),
+			(38493, 0, 1000, 38493),
+			(0, 1234, 2345, 0),
+			(1, 2, 1, 6),
+			(1, 4, 2, 6),
+			(1, 3, 1, 16),
+			(1, 6, 2, 18),
+			(1, 4, 1, 49),
+			(1, 8, 2, 50),
+			(10, 8, 2, 542),
+			(11, 8, 2, 596),
+			(1, 5, 1, 136),
+			(1, 5, 2, 11),
+			(2, 5, 2, 23),
+			(1, 50_000_000, 2_225_652, 5_709_098_764),
+		];
+		for (factor, numerator, denominator, expected) in vectors {
+			assert_eq!(fake_exponential(factor, numerator, denominator), U256::from(expected));
+		}
+	}
+
+	#[test]
+	fn test_calc_blob_base_fee() {
+		assert_eq!(calc_blob_base_fee(0), U256::one());
+		assert_eq!(calc_blob_base_fee(2_314_057), U256::one());
+		assert_eq!(calc_blob_base_fee(2_314_058), U256::from(2));
+		assert_eq!(calc_blob_base_fee(10 * 1024 * 1024), U256::from(23));
+		assert_eq!(calc_blob_base_fee(u64::MAX), U256::max_value());
+	}
+
+	#[test]
+	fn test_fake_exponential_zero_denominator() {
+		assert_eq!(fake_exponential(1, 5, 0), U256::max_value());
+		assert_eq!(fake_exponential(1, 0, 0), U256::max_value());
+		assert_eq!(fake_exponential(0, 5, 0), U256::zero());
+	}
+}
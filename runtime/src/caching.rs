@@ -0,0 +1,265 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::vec::Vec;
+use crate::{Capture, Stack, ExitError, ExitFatal, Opcode, Machine, ExitReason, Handler, BlockEnv, H160, H256, U256};
+use evm_core::{Context, CreateScheme, Transfer};
+
+/// Handler decorator caching `SLOAD`s and buffering `SSTORE`s of the inner
+/// handler.
+///
+/// Buffered writes are flushed to the inner handler when a frame is entered
+/// or exits successfully, and discarded when it reverts, so the inner handler
+/// only sees the last write of every slot in each frame. Writes of the top
+/// level frame stay buffered until `flush` is called.
+pub struct CachingHandler<H> {
+	inner: H,
+	reads: RefCell<BTreeMap<(H160, U256), U256>>,
+	writes: BTreeMap<(H160, U256), U256>,
+	error: Option<ExitError>,
+}
+
+impl<H: Handler> CachingHandler<H> {
+	/// Wrap `inner` with an empty cache.
+	pub const fn new(inner: H) -> Self {
+		Self {
+			inner,
+			reads: RefCell::new(BTreeMap::new()),
+			writes: BTreeMap::new(),
+			error: None,
+		}
+	}
+
+	/// Get a reference to the inner handler.
+	pub const fn inner(&self) -> &H {
+		&self.inner
+	}
+
+	/// Write buffered storage changes to the inner handler. Returns the first
+	/// error the inner handler raised, including while flushing on frame
+	/// entry or exit.
+	pub fn flush(&mut self) -> Result<(), ExitError> {
+		self.flush_writes();
+		self.error.take().map_or(Ok(()), Err)
+	}
+
+	/// Flush buffered changes and unwrap the inner handler.
+	pub fn into_inner(mut self) -> Result<H, ExitError> {
+		self.flush()?;
+		Ok(self.inner)
+	}
+
+	fn flush_writes(&mut self) {
+		for ((address, index), value) in core::mem::take(&mut self.writes) {
+			if let Err(e) = self.inner.set_storage(address, index, value) {
+				self.error.get_or_insert(e);
+			}
+		}
+	}
+}
+
+impl<H: Handler> Handler for CachingHandler<H> {
+	type CreateInterrupt = H::CreateInterrupt;
+	type CreateFeedback = H::CreateFeedback;
+	type CallInterrupt = H::CallInterrupt;
+	type CallFeedback = H::CallFeedback;
+
+	fn keccak256_h256(&self, data: &[u8]) -> H256 { self.inner.keccak256_h256(data) }
+	fn try_keccak256_h256(&self, data: &[u8]) -> Result<H256, ExitFatal> { self.inner.try_keccak256_h256(data) }
+
+	fn nonce(&self, address: H160) -> U256 { self.inner.nonce(address) }
+	fn balance(&self, address: H160) -> U256 { self.inner.balance(address) }
+	fn code_size(&self, address: H160) -> U256 { self.inner.code_size(address) }
+	fn code_hash(&self, address: H160) -> H256 { self.inner.code_hash(address) }
+	fn code(&self, address: H160) -> Vec<u8> { self.inner.code(address) }
+	fn has_code(&self, address: H160) -> bool { self.inner.has_code(address) }
+	fn prefetch(&self, addresses: &[H160], slots: &[(H160, H256)]) { self.inner.prefetch(addresses, slots) }
+	fn account_exists(&self, address: H160) -> bool { self.inner.account_exists(address) }
+	fn account_exists_for_create(&self, address: H160) -> bool { self.inner.account_exists_for_create(address) }
+	fn is_precompile(&self, address: H160) -> bool { self.inner.is_precompile(address) }
+	fn precompile_gas(&self, address: H160, input: &[u8]) -> Result<u64, ExitError> {
+		self.inner.precompile_gas(address, input)
+	}
+	fn valids(&self, address: H160) -> Vec<u8> { self.inner.valids(address) }
+	fn delegated_code(&self, address: H160) -> Option<H160> { self.inner.delegated_code(address) }
+
+	fn storage(&self, address: H160, index: U256) -> U256 {
+		if let Some(value) = self.writes.get(&(address, index)) {
+			return *value;
+		}
+
+		*self.reads.borrow_mut()
+			.entry((address, index))
+			.or_insert_with(|| self.inner.storage(address, index))
+	}
+
+	fn storage_batch(&self, address: H160, indices: &[U256]) -> Vec<U256> {
+		let missing: Vec<U256> = indices.iter().copied()
+			.filter(|index| !self.writes.contains_key(&(address, *index)))
+			.filter(|index| !self.reads.borrow().contains_key(&(address, *index)))
+			.collect();
+		if !missing.is_empty() {
+			let values = self.inner.storage_batch(address, &missing);
+			let mut reads = self.reads.borrow_mut();
+			for (index, value) in missing.into_iter().zip(values) {
+				reads.insert((address, index), value);
+			}
+		}
+
+		indices.iter().map(|index| self.storage(address, *index)).collect()
+	}
+
+	fn gas_left(&self) -> U256 { self.inner.gas_left() }
+	fn gas_price(&self) -> U256 { self.inner.gas_price() }
+	fn effective_gas_price(&self) -> U256 { self.inner.effective_gas_price() }
+	fn origin(&self) -> H160 { self.inner.origin() }
+	fn block_hash(&self, number: U256) -> H256 { self.inner.block_hash(number) }
+	fn block_number(&self) -> U256 { self.inner.block_number() }
+	fn block_number_u64(&self) -> u64 { self.inner.block_number_u64() }
+	fn block_coinbase(&self) -> H160 { self.inner.block_coinbase() }
+	fn block_timestamp(&self) -> U256 { self.inner.block_timestamp() }
+	fn block_difficulty(&self) -> U256 { self.inner.block_difficulty() }
+	fn block_gas_limit(&self) -> U256 { self.inner.block_gas_limit() }
+	fn block_gas_limit_u64(&self) -> u64 { self.inner.block_gas_limit_u64() }
+	fn chain_id(&self) -> U256 { self.inner.chain_id() }
+	fn block_base_fee_per_gas(&self) -> U256 { self.inner.block_base_fee_per_gas() }
+	fn block_env(&self) -> BlockEnv { self.inner.block_env() }
+	fn excess_blob_gas(&self) -> u64 { self.inner.excess_blob_gas() }
+	fn blob_base_fee(&self) -> U256 { self.inner.blob_base_fee() }
+
+	fn set_storage(&mut self, address: H160, index: U256, value: U256) -> Result<(), ExitError> {
+		self.writes.insert((address, index), value);
+		Ok(())
+	}
+
+	fn replace_storage(&mut self, address: H160, index: U256, value: U256) -> Result<U256, ExitError> {
+		// Not forwarded: the write is buffered here.
+		let previous = self.storage(address, index);
+		self.writes.insert((address, index), value);
+		Ok(previous)
+	}
+
+	fn log(&mut self, address: H160, topics: Vec<H256>, data: Vec<u8>) -> Result<(), ExitError> {
+		self.inner.log(address, topics, data)
+	}
+
+	fn log_count(&self) -> usize {
+		self.inner.log_count()
+	}
+
+	#[cfg(feature = "tracing")]
+	fn custom_event(&self, tag: u32, data: &[u8]) {
+		self.inner.custom_event(tag, data);
+	}
+
+	fn mark_delete(&mut self, address: H160, target: H160) -> Result<(), ExitError> {
+		self.inner.mark_delete(address, target)
+	}
+
+	fn selfdestruct(&mut self, address: H160, beneficiary: H160, balance: U256) -> Result<(), ExitError> {
+		self.inner.selfdestruct(address, beneficiary, balance)
+	}
+
+	fn is_selfdestructed(&self, address: H160) -> bool {
+		self.inner.is_selfdestructed(address)
+	}
+
+	fn is_cold_account(&self, address: H160) -> bool {
+		self.inner.is_cold_account(address)
+	}
+
+	fn warm_account(&mut self, address: H160) {
+		self.inner.warm_account(address);
+	}
+
+	fn inc_nonce(&mut self, address: H160) -> Result<bool, ExitError> {
+		self.inner.inc_nonce(address)
+	}
+
+	fn create(
+		&mut self,
+		caller: H160,
+		scheme: CreateScheme,
+		address: H160,
+		value: U256,
+		init_code: Vec<u8>,
+		target_gas: Option<u64>,
+	) -> Capture<(ExitReason, Option<H160>, Vec<u8>), Self::CreateInterrupt> {
+		self.inner.create(caller, scheme, address, value, init_code, target_gas)
+	}
+
+	fn create_sync(
+		&mut self,
+		interrupt: Self::CreateInterrupt,
+	) -> Capture<(ExitReason, Option<H160>, Vec<u8>), Self::CreateInterrupt> {
+		self.inner.create_sync(interrupt)
+	}
+
+	fn create_depth(&self) -> usize {
+		self.inner.create_depth()
+	}
+
+	fn create_feedback(&mut self, feedback: Self::CreateFeedback) -> Result<(), ExitError> {
+		self.inner.create_feedback(feedback)
+	}
+
+	fn transfer(&mut self, transfer: &Transfer) -> Result<(), ExitError> {
+		self.inner.transfer(transfer)
+	}
+
+	fn call(
+		&mut self,
+		code_address: H160,
+		transfer: Option<Transfer>,
+		input: Vec<u8>,
+		target_gas: Option<u64>,
+		is_static: bool,
+		context: Context,
+	) -> Capture<(ExitReason, Vec<u8>), Self::CallInterrupt> {
+		self.inner.call(code_address, transfer, input, target_gas, is_static, context)
+	}
+
+	fn call_sync(&mut self, interrupt: Self::CallInterrupt) -> Capture<(ExitReason, Vec<u8>), Self::CallInterrupt> {
+		self.inner.call_sync(interrupt)
+	}
+
+	fn call_feedback(&mut self, feedback: Self::CallFeedback) -> Result<(), ExitError> {
+		self.inner.call_feedback(feedback)
+	}
+
+	fn enter_frame(&mut self) {
+		// The parent's writes belong outside of the inner handler's new frame.
+		self.flush_writes();
+		self.inner.enter_frame();
+	}
+
+	fn exit_frame(&mut self, reverted: bool) {
+		if reverted {
+			self.writes.clear();
+		} else {
+			self.flush_writes();
+		}
+		// The frame may have changed storage without going through the cache.
+		self.reads.get_mut().clear();
+		self.inner.exit_frame(reverted);
+	}
+
+	fn pre_validate(&mut self, context: &Context, opcode: Opcode, stack: &Stack) -> Result<(), ExitError> {
+		self.inner.pre_validate(context, opcode, stack)
+	}
+
+	fn record_cost(&mut self, cost: u64) -> Result<(), ExitError> {
+		self.inner.record_cost(cost)
+	}
+
+	fn record_refund(&mut self, refund: i64) -> Result<(), ExitError> {
+		self.inner.record_refund(refund)
+	}
+
+	fn preview_gas_cost(&self, context: &Context, opcode: Opcode, machine: &Machine) -> Option<u64> {
+		self.inner.preview_gas_cost(context, opcode, machine)
+	}
+
+	fn other(&mut self, opcode: Opcode, machine: &mut Machine) -> Result<(), ExitError> {
+		self.inner.other(opcode, machine)
+	}
+}
@@ -0,0 +1,41 @@
+use sha3::{Digest, Keccak256};
+use crate::{H160, H256, U256};
+
+/// Address of a contract deployed by `CREATE`: the last 20 bytes of
+/// `keccak256(rlp([caller, nonce]))`, `nonce` being the one of `caller`
+/// before the create increments it.
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub fn create_address(caller: H160, nonce: U256) -> H160 {
+	let nonce = H256::from(nonce);
+	let nonce = &nonce[nonce.as_bytes().iter().take_while(|byte| **byte == 0).count()..];
+	// At most 21 + 33 bytes: the list always has a short prefix.
+	let mut preimage = [0_u8; 55];
+	preimage[1] = 0x80 + 20;
+	preimage[2..22].copy_from_slice(caller.as_bytes());
+	let len = match nonce {
+		[byte] if *byte < 0x80 => {
+			preimage[22] = *byte;
+			23
+		},
+		_ => {
+			preimage[22] = 0x80 + nonce.len() as u8;
+			preimage[23..23 + nonce.len()].copy_from_slice(nonce);
+			23 + nonce.len()
+		},
+	};
+	preimage[0] = 0xc0 + (len - 1) as u8;
+	H160::from(H256::from_slice(Keccak256::digest(&preimage[..len]).as_slice()))
+}
+
+/// Address of a contract deployed by `CREATE2` (EIP-1014): the last 20 bytes
+/// of `keccak256(0xff ++ caller ++ salt ++ keccak256(init_code))`.
+#[must_use]
+pub fn create2_address(caller: H160, salt: H256, code_hash: H256) -> H160 {
+	let mut preimage = [0_u8; 85];
+	preimage[0] = 0xff;
+	preimage[1..21].copy_from_slice(caller.as_bytes());
+	preimage[21..53].copy_from_slice(salt.as_bytes());
+	preimage[53..].copy_from_slice(code_hash.as_bytes());
+	H160::from(H256::from_slice(Keccak256::digest(&preimage).as_slice()))
+}
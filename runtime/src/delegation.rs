@@ -0,0 +1,17 @@
+use crate::H160;
+
+/// Prefix of an EIP-7702 delegation designator, followed by the 20 byte
+/// address of the delegated code.
+pub const DELEGATION_PREFIX: [u8; 3] = [0xef, 0x01, 0x00];
+
+/// Parse the code of an account as an EIP-7702 delegation designator,
+/// returning the designated address. Returns `None` if the code is not
+/// exactly `0xef0100 ++ address`.
+#[must_use]
+pub fn delegation_designator(code: &[u8]) -> Option<H160> {
+	if code.len() != DELEGATION_PREFIX.len() + 20 || !code.starts_with(&DELEGATION_PREFIX) {
+		return None;
+	}
+
+	Some(H160::from_slice(&code[DELEGATION_PREFIX.len()..]))
+}
@@ -33,7 +33,8 @@ pub fn eval<H: Handler>(state: &mut Runtime, opcode: Opcode, handler: &mut H) ->
 		Opcode::ADDRESS => system::address(state),
 		Opcode::BALANCE => system::balance(state, handler),
 		Opcode::SELFBALANCE => system::selfbalance(state, handler),
-		Opcode::BASEFEE => system::basefee(state, handler),
+		Opcode::BASEFEE if state.cfg().has_base_fee => system::basefee(state, handler),
+		Opcode::BLOBBASEFEE if state.cfg().has_blob_base_fee => system::blobbasefee(state, handler),
 		Opcode::ORIGIN => system::origin(state, handler),
 		Opcode::CALLER => system::caller(state),
 		Opcode::CALLVALUE => system::callvalue(state),
@@ -1,11 +1,11 @@
 use core::cmp::min;
 use alloc::vec::Vec;
-use crate::{Runtime, ExitError, Handler, Capture, Transfer, ExitReason, CreateScheme, CallScheme, Context, ExitSucceed, ExitFatal, H160, H256, U256};
+use crate::{all_but_one_64th, create_address, create2_address, empty_code_hash, BlockEnv, Runtime, ExitError, Handler, Capture, Transfer, ExitReason, CreateScheme, CallScheme, Context, ExitSucceed, ExitFatal, H160, H256, U256};
 use super::Control;
 use evm_core::event;
 
 #[cfg(feature = "tracing")]
-use evm_core::{Event, SStoreTrace, SLoadTrace, tracing::with as with};
+use evm_core::{Event, SStoreTrace, SLoadTrace, IncrementNonceTrace};
 
 
 pub fn sha3<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
@@ -20,14 +20,22 @@ pub fn sha3<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
 		runtime.machine.memory_mut().get(from, len)
 	};
 
-	let ret = handler.keccak256_h256(data.as_slice()); //Keccak256::digest(data.as_slice());
+	let ret = try_or_fail!(handler.try_keccak256_h256(data.as_slice())); //Keccak256::digest(data.as_slice());
 	push!(runtime, ret); //H256::from_slice(ret.as_slice()));
 
 	Control::Continue
 }
 
+/// Block fields, read through `Handler::block_env` on first use only, so
+/// that handlers reading them one by one are not queried again by every
+/// block opcode of the frame.
+fn block_env<'a, H: Handler>(runtime: &'a mut Runtime, handler: &H) -> &'a BlockEnv {
+	runtime.block_env.get_or_insert_with(|| handler.block_env())
+}
+
 pub fn chainid<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
-	push_u256!(runtime, handler.chain_id());
+	let chain_id = block_env(runtime, handler).chain_id;
+	push_u256!(runtime, chain_id);
 
 	Control::Continue
 }
@@ -52,8 +60,15 @@ pub fn selfbalance<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H>
 	Control::Continue
 }
 
-pub fn basefee<H: Handler>(runtime: &mut Runtime, _handler: &H) -> Control<H> {
-	push_u256!(runtime, U256::zero());
+pub fn basefee<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
+	let base_fee = block_env(runtime, handler).base_fee;
+	push_u256!(runtime, base_fee);
+
+	Control::Continue
+}
+
+pub fn blobbasefee<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
+	push_u256!(runtime, handler.blob_base_fee());
 
 	Control::Continue
 }
@@ -81,23 +96,126 @@ pub fn callvalue<H: Handler>(runtime: &mut Runtime) -> Control<H> {
 }
 
 pub fn gasprice<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
-	let mut ret = H256::default();
-	handler.gas_price().to_big_endian(&mut ret[..]);
-	push!(runtime, ret);
+	let price = if runtime.cfg().has_base_fee {
+		handler.effective_gas_price()
+	} else {
+		handler.gas_price()
+	};
+	push_u256!(runtime, price);
 
 	Control::Continue
 }
 
+/// Refund of the first `SELFDESTRUCT` of a contract, before London.
+const SELFDESTRUCT_REFUND: i64 = 24000;
+
+/// Charge `cost` through the handler, unless gas is ignored. Counts
+/// towards `Config::per_opcode_gas_cap`, failing without charging if the
+/// opcode would exceed it.
+fn record_cost<H: Handler>(runtime: &mut Runtime, handler: &mut H, cost: u64) -> Result<(), ExitError> {
+	if let Some(cap) = runtime.cfg().per_opcode_gas_cap {
+		let charged = runtime.opcode_gas.saturating_add(cost);
+		if charged > cap {
+			return Err(ExitError::OutOfGas);
+		}
+		runtime.opcode_gas = charged;
+	}
+	match handler.record_cost(cost) {
+		Err(ExitError::OutOfGas) if runtime.cfg().ignore_gas => Ok(()),
+		result => result,
+	}
+}
+
+/// Gas a call forwards to its callee: the requested gas, capped by the l64
+/// rule when enabled, plus the stipend of value transfers. The stipend is
+/// added after the cap and is not charged to the caller.
+fn forwarded_gas<H: Handler>(runtime: &Runtime, handler: &H, target_gas: Option<u64>, value: U256) -> u64 {
+	let available = available_gas(handler);
+	let gas = if runtime.cfg().call_l64_after_gas {
+		let max_gas = all_but_one_64th(available);
+		target_gas.map_or(max_gas, |gas| min(gas, max_gas))
+	} else {
+		target_gas.unwrap_or(available)
+	};
+
+	if value.is_zero() {
+		gas
+	} else {
+		gas.saturating_add(runtime.cfg().call_stipend)
+	}
+}
+
+/// Check that a call to the precompile at `address` forwards the gas it
+/// requires on `input`, or the handler's error rejecting the input.
+fn check_precompile_gas<H: Handler>(
+	runtime: &Runtime,
+	handler: &H,
+	address: H160,
+	input: &[u8],
+	target_gas: Option<u64>,
+	value: U256,
+) -> Result<(), ExitError> {
+	let required = handler.precompile_gas(address, input)?;
+	if !runtime.cfg().ignore_gas && forwarded_gas(runtime, handler, target_gas, value) < required {
+		return Err(ExitError::OutOfGas);
+	}
+	Ok(())
+}
+
+/// Address a create deploys to. Must be called before the nonce of a
+/// legacy create is incremented; it is then passed to `Handler::create`.
+fn created_address<H: Handler>(handler: &H, scheme: &CreateScheme) -> H160 {
+	match *scheme {
+		CreateScheme::Legacy { caller } => create_address(caller, handler.nonce(caller)),
+		CreateScheme::Create2 { caller, code_hash, salt } => create2_address(caller, salt, code_hash),
+		CreateScheme::Fixed(address) => address,
+	}
+}
+
+/// Gas left in the handler, saturated to `u64`.
+fn available_gas<H: Handler>(handler: &H) -> u64 {
+	let gas_left = handler.gas_left();
+	if gas_left > U256::from(u64::MAX) { u64::MAX } else { gas_left.as_u64() }
+}
+
+/// Reject account code larger than `Config::max_code_size`.
+fn check_code_size(runtime: &Runtime, size: U256) -> Result<(), ExitFatal> {
+	match runtime.cfg().max_code_size {
+		Some(limit) if size > U256::from(limit) => Err(ExitFatal::CodeSizeLimit),
+		_ => Ok(()),
+	}
+}
+
 pub fn extcodesize<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
 	pop!(runtime, address);
-	push_u256!(runtime, handler.code_size(address.into()));
+	let size = handler.code_size(address.into());
+	try_or_fail!(check_code_size(runtime, size));
+	push_u256!(runtime, size);
 
 	Control::Continue
 }
 
 pub fn extcodehash<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
 	pop!(runtime, address);
-	push!(runtime, handler.code_hash(address.into()));
+	let address = H160::from(address);
+	#[cfg(feature = "code-hash-cache")]
+	if let Some(hash) = runtime.code_hashes.get(&address) {
+		push!(runtime, *hash);
+		return Control::Continue;
+	}
+
+	// EIP-1052: zero for non-existent (or, after EIP-161, empty) accounts.
+	// Precompiles exist but have no code.
+	let hash = if runtime.cfg().precompile_has_empty_code_hash && handler.is_precompile(address) {
+		empty_code_hash()
+	} else if handler.account_exists(address) {
+		handler.code_hash(address)
+	} else {
+		H256::zero()
+	};
+	#[cfg(feature = "code-hash-cache")]
+	runtime.code_hashes.insert(address, hash);
+	push!(runtime, hash);
 
 	Control::Continue
 }
@@ -110,12 +228,15 @@ pub fn extcodecopy<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H>
 	let code_offset = as_usize_or_fail!(code_offset);
 	let len = as_usize_or_fail!(len);
 
+	let code = handler.code(address.into());
+	try_or_fail!(check_code_size(runtime, U256::from(code.len())));
+
 	try_or_fail!(runtime.machine.memory_mut().resize_offset(memory_offset, len));
 	match runtime.machine.memory_mut().copy_large(
 		memory_offset,
 		code_offset,
 		len,
-		&handler.code(address.into())
+		&code
 	) {
 		Ok(()) => (),
 		Err(e) => return Control::Exit(e.into()),
@@ -154,33 +275,50 @@ pub fn returndatacopy<H: Handler>(runtime: &mut Runtime) -> Control<H> {
 
 pub fn blockhash<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
 	pop_u256!(runtime, number);
-	push!(runtime, handler.block_hash(number));
+	// Only the 256 most recent complete blocks are available.
+	let current = handler.block_number_u64();
+	let hash = if number > U256::from(u64::MAX) {
+		H256::default()
+	} else {
+		let number = number.as_u64();
+		if number >= current || number.saturating_add(256) < current {
+			H256::default()
+		} else {
+			handler.block_hash(number.into())
+		}
+	};
+	push!(runtime, hash);
 
 	Control::Continue
 }
 
 pub fn coinbase<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
-	push!(runtime, handler.block_coinbase().into());
+	let coinbase = block_env(runtime, handler).coinbase;
+	push!(runtime, coinbase.into());
 	Control::Continue
 }
 
 pub fn timestamp<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
-	push_u256!(runtime, handler.block_timestamp());
+	let timestamp = block_env(runtime, handler).timestamp;
+	push_u256!(runtime, timestamp);
 	Control::Continue
 }
 
 pub fn number<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
-	push_u256!(runtime, handler.block_number());
+	let number = block_env(runtime, handler).number;
+	push_u256!(runtime, number);
 	Control::Continue
 }
 
 pub fn difficulty<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
-	push_u256!(runtime, handler.block_difficulty());
+	let difficulty = block_env(runtime, handler).difficulty;
+	push_u256!(runtime, difficulty);
 	Control::Continue
 }
 
 pub fn gaslimit<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
-	push_u256!(runtime, handler.block_gas_limit());
+	let gas_limit = block_env(runtime, handler).gas_limit;
+	push_u256!(runtime, U256::from(gas_limit));
 	Control::Continue
 }
 
@@ -210,8 +348,14 @@ pub fn sstore<H: Handler>(runtime: &mut Runtime, handler: &mut H) -> Control<H>
 		}
 	));
 
-	match handler.set_storage(runtime.context.address, index, value) {
-		Ok(()) => Control::Continue,
+	match handler.replace_storage(runtime.context.address, index, value) {
+		Ok(previous) => {
+			#[cfg(feature = "tracing")]
+			runtime.record_storage_write(index, previous, value);
+			#[cfg(not(feature = "tracing"))]
+			let _ = previous;
+			Control::Continue
+		},
 		Err(e) => Control::Exit(e.into()),
 	}
 }
@@ -242,6 +386,10 @@ pub fn log<H: Handler>(runtime: &mut Runtime, n: u8, handler: &mut H) -> Control
 		}
 	}
 
+	if matches!(runtime.cfg().max_logs, Some(limit) if handler.log_count() >= limit) {
+		return Control::Exit(ExitError::TooManyLogs.into());
+	}
+
 	match handler.log(runtime.context.address, topics, data) {
 		Ok(()) => Control::Continue,
 		Err(e) => Control::Exit(e.into()),
@@ -250,11 +398,30 @@ pub fn log<H: Handler>(runtime: &mut Runtime, n: u8, handler: &mut H) -> Control
 
 pub fn suicide<H: Handler>(runtime: &mut Runtime, handler: &mut H) -> Control<H> {
 	pop!(runtime, target);
+	let target = H160::from(target);
+
+	if runtime.cfg().gas_suicide_new_account != 0 && !handler.account_exists(target) {
+		let transfers_value = !handler.balance(runtime.context.address).is_zero();
+		if runtime.cfg().empty_considered_exists || transfers_value {
+			let cost = runtime.cfg().gas_suicide_new_account;
+			try_or_fail!(record_cost(runtime, handler, cost));
+		}
+	}
+
+	let balance = if target == runtime.context.address && !runtime.cfg().has_eip6780 {
+		U256::zero()
+	} else {
+		handler.balance(runtime.context.address)
+	};
+	let refund = runtime.cfg().has_selfdestruct_refund && !handler.is_selfdestructed(runtime.context.address);
 
-	match handler.mark_delete(runtime.context.address, target.into()) {
+	match handler.selfdestruct(runtime.context.address, target, balance) {
 		Ok(()) => (),
 		Err(e) => return Control::Exit(e.into()),
 	}
+	if refund {
+		try_or_fail!(handler.record_refund(SELFDESTRUCT_REFUND));
+	}
 
 	Control::Exit(ExitSucceed::Suicided.into())
 }
@@ -280,7 +447,7 @@ pub fn create<H: Handler>(
 	let scheme = if is_create2 {
 		pop!(runtime, salt);
 		//let code_hash = H256::from_slice(Keccak256_digest(&code)); //Keccak256::digest(&code).as_slice());
-		let code_hash = handler.keccak256_h256(&code);
+		let code_hash = try_or_fail!(handler.try_keccak256_h256(&code));
 		CreateScheme::Create2 {
 			caller: runtime.context.address,
 			salt,
@@ -292,9 +459,45 @@ pub fn create<H: Handler>(
 		}
 	};
 
-	match handler.create(runtime.context.address, scheme, value, code, None) {
-		Capture::Exit((reason, address, _return_data)) => {
-			save_created_address(runtime, reason, address, handler)
+	let address = created_address(handler, &scheme);
+	// Checked before entering the frame, so that the nonce increment
+	// survives a failing child.
+	let error = if !value.is_zero() && handler.balance(runtime.context.address) < value {
+		Some(ExitError::OutOfFund)
+	} else if matches!(runtime.cfg().create_depth_limit, Some(limit) if handler.create_depth() >= limit) {
+		Some(ExitError::CallTooDeep)
+	} else if runtime.cfg().create_increase_nonce {
+		match handler.inc_nonce(runtime.context.address) {
+			Ok(incremented) => {
+				if incremented {
+					event!(Event::IncrementNonce(IncrementNonceTrace { address: runtime.context.address }));
+				}
+				None
+			},
+			Err(error) => Some(error),
+		}
+	} else {
+		None
+	};
+	let error = error.or_else(|| {
+		(runtime.cfg().has_create_collision_check && handler.account_exists_for_create(address))
+			.then_some(ExitError::CreateCollision)
+	});
+
+	handler.enter_frame();
+	if let Some(error) = error {
+		return save_created_address(runtime, error.into(), None, Vec::new(), handler);
+	}
+
+	let target_gas = if runtime.cfg().call_l64_after_gas {
+		Some(all_but_one_64th(available_gas(handler)))
+	} else {
+		None
+	};
+	runtime.create_frames += 1;
+	match handler.create(runtime.context.address, scheme, address, value, code, target_gas) {
+		Capture::Exit((reason, address, return_data)) => {
+			save_created_address(runtime, reason, address, return_data, handler)
 		},
 		Capture::Trap(interrupt) => {
 			// The created contract's address will be push by the method save_created_address()
@@ -304,6 +507,16 @@ pub fn create<H: Handler>(
 	}
 }
 
+/// Check the size of the code deployed by a create against
+/// `Config::create_contract_limit` (EIP-170), then charge its deposit.
+fn deposit_code<H: Handler>(runtime: &mut Runtime, handler: &mut H, len: usize) -> Result<(), ExitError> {
+	if matches!(runtime.cfg().create_contract_limit, Some(limit) if len > limit) {
+		return Err(ExitError::CreateContractLimit);
+	}
+	let cost = runtime.cfg().gas_code_deposit.saturating_mul(len as u64);
+	record_cost(runtime, handler, cost)
+}
+
 pub fn call<'config, H: Handler>(
 	runtime: &mut Runtime,
 	scheme: CallScheme,
@@ -329,14 +542,16 @@ pub fn call<'config, H: Handler>(
 		},
 	};
 
-	// https://app.zenhub.com/workspaces/solana-evm-6007c75a9dc141001100ccb8/issues/cyber-core/solana-program-library/132
-	// out_offset and out_len parameters will be read in save_return_value()
-	pop_u256!(runtime, in_offset, in_len/*, out_offset, out_len*/);
+	pop_u256!(runtime, in_offset, in_len, out_offset, out_len);
 	let in_offset = as_usize_or_fail!(in_offset);
 	let in_len = as_usize_or_fail!(in_len);
-	
+	let out_offset = as_usize_or_fail!(out_offset);
+	let out_len = as_usize_or_fail!(out_len);
+
 	try_or_fail!(runtime.machine.memory_mut().resize_offset(in_offset, in_len));
-	// try_or_fail!(runtime.machine.memory_mut().resize_offset(out_offset, out_len));
+	try_or_fail!(runtime.machine.memory_mut().resize_offset(out_offset, out_len));
+	// Kept on the runtime, so that it survives an interrupt.
+	runtime.call_output = Some((out_offset, out_len));
 
 	let input = if in_len == 0 {
 		Vec::new()
@@ -378,7 +593,44 @@ pub fn call<'config, H: Handler>(
 		None
 	};
 
-	match handler.call(to.into(), transfer, input, gas, scheme == CallScheme::StaticCall, context) {
+	if !value.is_zero() && handler.balance(runtime.context.address) < value {
+		handler.enter_frame();
+		return save_return_value(runtime, ExitError::OutOfFund.into(), Vec::new(), handler);
+	}
+
+	let is_precompile = handler.is_precompile(to.into());
+	if is_precompile {
+		if let Err(e) = check_precompile_gas(runtime, handler, to.into(), &input, gas, value) {
+			handler.enter_frame();
+			return save_return_value(runtime, e.into(), Vec::new(), handler);
+		}
+	}
+
+	// EIP-7702: the designated code runs in the context of the delegating
+	// account, so only the code address changes. Precompiles have no code
+	// to look at: the handler runs them, unless designated, in which case
+	// the code is empty.
+	let delegate = if runtime.cfg().has_eip7702 && !is_precompile {
+		handler.delegated_code(to.into())
+	} else {
+		None
+	};
+	let code_address = delegate.unwrap_or_else(|| to.into());
+	if runtime.cfg().max_code_size.is_some() && !is_precompile {
+		try_or_fail!(check_code_size(runtime, handler.code_size(code_address)));
+	}
+
+	handler.enter_frame();
+	if let Some(transfer) = &transfer {
+		if let Err(e) = handler.transfer(transfer) {
+			return save_return_value(runtime, e.into(), Vec::new(), handler);
+		}
+	}
+	runtime.call_frames += 1;
+	if delegate.is_some_and(|delegate| handler.is_precompile(delegate)) {
+		return save_return_value(runtime, ExitSucceed::Stopped.into(), Vec::new(), handler);
+	}
+	match handler.call(code_address, transfer, input, gas, scheme == CallScheme::StaticCall, context) {
 		Capture::Exit((reason, return_data)) => {
 			save_return_value(runtime, reason, return_data, handler)
 		},
@@ -390,15 +642,59 @@ pub fn call<'config, H: Handler>(
 	}
 }
 
-/// save created contract address into parent runtime
+/// Fail a child frame whose return data exceeds `Config::max_return_data`,
+/// unless it already exited fatally.
+fn limit_return_data(runtime: &Runtime, reason: ExitReason, return_data: Vec<u8>) -> (ExitReason, Vec<u8>) {
+	match runtime.cfg().max_return_data {
+		Some(limit) if return_data.len() > limit && !matches!(reason, ExitReason::Fatal(_)) => {
+			(ExitError::ReturnDataLimit.into(), Vec::new())
+		},
+		_ => (reason, return_data),
+	}
+}
+
+/// save created contract address into parent runtime.
+///
+/// A deserialized runtime whose configuration has not been restored exits
+/// with `ExitFatal::ConfigNotSet` and is left unchanged.
 pub fn save_created_address<'config, H: Handler>(
 	runtime: &mut Runtime,
 	reason : ExitReason,
 	address: Option<H160>,
-	// return_data : Vec<u8>,
-	_handler: & H
+	return_data: Vec<u8>,
+	handler: &mut H
 ) -> Control<H> {
-	// runtime.return_data_buffer = return_data;
+	if runtime.config().is_none() {
+		return Control::Exit(ExitFatal::ConfigNotSet.into());
+	}
+	// Checked here so that interrupted creates are limited and pay the
+	// deposit too.
+	let reason = if reason.is_succeed() {
+		match deposit_code(runtime, handler, return_data.len()) {
+			Ok(()) => reason,
+			Err(e) => e.into(),
+		}
+	} else {
+		reason
+	};
+	// The output of a successful create is code, not return data.
+	let (reason, return_data) = match reason {
+		ExitReason::Revert(_) => limit_return_data(runtime, reason, return_data),
+		_ => (reason, return_data),
+	};
+	handler.exit_frame(!reason.is_succeed());
+	// The child may have changed code, balances or nonces.
+	#[cfg(feature = "code-hash-cache")]
+	runtime.code_hashes.clear();
+	// EIP-2929: the created address stays warm even if the creation failed.
+	if let Some(address) = address {
+		handler.warm_account(address);
+	}
+
+	// EIP-211: only the revert reason of a failed create is returned.
+	if let ExitReason::Revert(_) = reason {
+		runtime.return_data_buffer = return_data;
+	}
 	let create_address: H256 = address.map(|a| a.into()).unwrap_or_default();
 
 	match reason {
@@ -423,19 +719,48 @@ pub fn save_created_address<'config, H: Handler>(
 
 }
 
-/// save return_value into parent runtime
+/// Output region of a `CALL` left on the stack, as the runtime did before
+/// `Runtime::call_output`.
+fn legacy_call_output(runtime: &mut Runtime) -> Result<(usize, usize), ExitReason> {
+	let stack = runtime.machine.stack_mut();
+	let (offset, len) = (stack.pop_u256()?, stack.pop_u256()?);
+	if offset > U256::from(usize::MAX) || len > U256::from(usize::MAX) {
+		return Err(ExitFatal::NotSupported.into());
+	}
+	let (offset, len) = (offset.as_usize(), len.as_usize());
+	runtime.machine.memory_mut().resize_offset(offset, len)?;
+	Ok((offset, len))
+}
+
+/// save return_value into parent runtime, writing it to the output region
+/// captured by the `CALL`, or popped from the stack for a `CALL` suspended
+/// in the legacy serialized layout.
+///
+/// A deserialized runtime whose configuration has not been restored exits
+/// with `ExitFatal::ConfigNotSet` and is left unchanged.
 pub fn save_return_value<'config, H: Handler>(
 	runtime: &mut Runtime,
 	reason : ExitReason,
 	return_data : Vec<u8>,
-	_handler: & H
+	handler: &mut H
 	) -> Control<H> {
-
-	pop_u256!(runtime, out_offset, out_len);
-	let out_offset = as_usize_or_fail!(out_offset);
-	let out_len = as_usize_or_fail!(out_len);
-
-	try_or_fail!(runtime.machine.memory_mut().resize_offset(out_offset, out_len));
+	if runtime.config().is_none() {
+		return Control::Exit(ExitFatal::ConfigNotSet.into());
+	}
+	let (reason, return_data) = limit_return_data(runtime, reason, return_data);
+	handler.exit_frame(!reason.is_succeed());
+	// The child may have changed code, balances or nonces.
+	#[cfg(feature = "code-hash-cache")]
+	runtime.code_hashes.clear();
+
+	let (out_offset, out_len) = match runtime.call_output.take() {
+		Some(output) => output,
+		// Suspended by a runtime deserialized from the legacy layout.
+		None => match legacy_call_output(runtime) {
+			Ok(output) => output,
+			Err(e) => return Control::Exit(e),
+		},
+	};
 
         {  // this block uses the given alignment to match the original code.
 			runtime.return_data_buffer = return_data;
@@ -0,0 +1,9 @@
+use core::cmp::min;
+use crate::U256;
+
+/// Effective gas price of an EIP-1559 transaction: the base fee plus the
+/// priority fee, capped by the max fee.
+#[must_use]
+pub fn effective_gas_price(base_fee: U256, max_priority_fee_per_gas: U256, max_fee_per_gas: U256) -> U256 {
+	min(base_fee.saturating_add(max_priority_fee_per_gas), max_fee_per_gas)
+}
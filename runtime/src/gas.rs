@@ -0,0 +1,55 @@
+use core::convert::TryFrom;
+use crate::{ExitError, Opcode, Stack, U256};
+
+/// Gas left after withholding one 64th of `gas`, the most a call or create
+/// may forward (EIP-150).
+#[must_use]
+pub const fn all_but_one_64th(gas: u64) -> u64 {
+	gas - gas / 64
+}
+
+/// Gas of a memory of `words` 32-byte words, `3 * words + words² / 512`,
+/// charged for its expansion as the difference with the previous size.
+/// Saturates to `u64::MAX`.
+#[must_use]
+pub fn memory_gas(words: usize) -> u64 {
+	let words = u128::try_from(words).unwrap_or(u128::MAX);
+	let gas = words.checked_mul(words)
+		.map(|square| square / 512)
+		.and_then(|quadratic| quadratic.checked_add(words.checked_mul(3)?));
+	gas.and_then(|gas| u64::try_from(gas).ok()).unwrap_or(u64::MAX)
+}
+
+/// Memory expansion gas `opcode` would be charged, given its operands on
+/// `stack`, on a memory of `words` 32-byte words. Saturates to `u64::MAX`.
+pub fn memory_expansion_gas(opcode: Opcode, stack: &Stack, words: usize) -> Result<u64, ExitError> {
+	let end = accessed_memory_end(opcode, stack)?;
+	let new_words = if end > U256::from(usize::MAX) { usize::MAX } else { end.as_usize().div_ceil(32) };
+	Ok(memory_gas(new_words.max(words)).saturating_sub(memory_gas(words)))
+}
+
+/// End of the memory `opcode` accesses, given its operands on `stack`,
+/// zero if it accesses none.
+fn accessed_memory_end(opcode: Opcode, stack: &Stack) -> Result<U256, ExitError> {
+	let ranges: &[(usize, usize)] = match opcode {
+		Opcode::MLOAD | Opcode::MSTORE => return Ok(range_end(stack.peek(0)?, U256::from(32))),
+		Opcode::MSTORE8 => return Ok(range_end(stack.peek(0)?, U256::one())),
+		// `Opcode(0xa0..=0xa4)` are `LOG0` to `LOG4`.
+		Opcode::SHA3 | Opcode::RETURN | Opcode::REVERT | Opcode(0xa0..=0xa4) => &[(0, 1)],
+		Opcode::CALLDATACOPY | Opcode::CODECOPY | Opcode::RETURNDATACOPY => &[(0, 2)],
+		Opcode::EXTCODECOPY => &[(1, 3)],
+		Opcode::CREATE | Opcode::CREATE2 => &[(1, 2)],
+		Opcode::CALL | Opcode::CALLCODE => &[(3, 4), (5, 6)],
+		Opcode::DELEGATECALL | Opcode::STATICCALL => &[(2, 3), (4, 5)],
+		_ => &[],
+	};
+	ranges.iter().try_fold(U256::zero(), |end, &(offset, len)| {
+		Ok(end.max(range_end(stack.peek(offset)?, stack.peek(len)?)))
+	})
+}
+
+/// End of `len` bytes of memory at `offset`, zero for an empty range, which
+/// does not expand memory.
+fn range_end(offset: U256, len: U256) -> U256 {
+	if len.is_zero() { U256::zero() } else { offset.saturating_add(len) }
+}
@@ -1,8 +1,33 @@
 use alloc::vec::Vec;
 use crate::{Capture, Stack, ExitError, Opcode,
-			Machine, ExitReason,
+			Machine, ExitReason, ExitFatal,
 			H160, H256, U256};
 use evm_core::{Context, CreateScheme, Transfer};
+use crate::{delegation_designator, calc_blob_base_fee};
+#[cfg(feature = "tracing")]
+use crate::{Event, CustomTrace};
+
+/// Environmental block fields.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BlockEnv {
+	/// Block coinbase.
+	pub coinbase: H160,
+	/// Block timestamp.
+	pub timestamp: U256,
+	/// Block number.
+	pub number: U256,
+	/// Block difficulty.
+	pub difficulty: U256,
+	/// Block gas limit.
+	pub gas_limit: u64,
+	/// Chain ID.
+	pub chain_id: U256,
+	/// Block base fee per gas.
+	pub base_fee: U256,
+	/// Randomness beacon output of the previous block (EIP-4399). Defaults
+	/// to the difficulty.
+	pub prev_randao: H256,
+}
 
 /// EVM context handler.
 pub trait Handler {
@@ -17,29 +42,88 @@ pub trait Handler {
 
 	/// Get keccak hash from data.
 	fn keccak256_h256(&self, data: &[u8]) -> H256;
+	/// Get keccak hash from data, for handlers whose hashing can fail. An
+	/// error exits the runtime with it, which `SHA3` and `CREATE2` use
+	/// instead of `keccak256_h256`. Defaults to `keccak256_h256`.
+	fn try_keccak256_h256(&self, data: &[u8]) -> Result<H256, ExitFatal> {
+		Ok(self.keccak256_h256(data))
+	}
 
 	/// Get account nonce
 	fn nonce(&self, address: H160) -> U256;
 	/// Get balance of address.
 	fn balance(&self, address: H160) -> U256;
-	/// Get code size of address.
+	/// Get code size of address. `EXTCODESIZE` only ever queries this and
+	/// never `code`, so implementations should answer it from account
+	/// metadata rather than loading the full code.
 	fn code_size(&self, address: H160) -> U256;
-	/// Get code hash of address.
+	/// Get code hash of address. `EXTCODEHASH` only queries this for
+	/// existing accounts, which must hash to `crate::empty_code_hash` if they
+	/// have no code.
 	fn code_hash(&self, address: H160) -> H256;
 	/// Get code of address.
 	fn code(&self, address: H160) -> Vec<u8>;
+	/// Whether `address` has code. The runtime never loads the code of a
+	/// call target, this lets `call` implementations skip loading it for
+	/// plain transfers to accounts without code. Defaults to loading it.
+	fn has_code(&self, address: H160) -> bool {
+		!self.code(address).is_empty()
+	}
+	/// Hint that `addresses` and storage `slots` are about to be read, so
+	/// that remote state can be fetched ahead of time. Has no effect on
+	/// execution. Does nothing by default.
+	fn prefetch(&self, _addresses: &[H160], _slots: &[(H160, H256)]) {}
+	/// Whether an account exists at `address`. Defaults to the account not
+	/// being empty, as defined by EIP-161.
+	fn account_exists(&self, address: H160) -> bool {
+		!self.balance(address).is_zero() || !self.nonce(address).is_zero() || !self.code_size(address).is_zero()
+	}
+	/// Whether deploying a contract at `address` collides with an existing
+	/// account, as defined by EIP-684: one with a nonce or code.
+	fn account_exists_for_create(&self, address: H160) -> bool {
+		!self.nonce(address).is_zero() || self.has_code(address)
+	}
+	/// Whether `address` is a precompile.
+	fn is_precompile(&self, _address: H160) -> bool {
+		false
+	}
+	/// Gas required to run the precompile at `address` on `input`. A call
+	/// forwarding less fails with `OutOfGas` without reaching `call`, and so
+	/// does a call whose input is rejected with an error, such as lengths too
+	/// large to price. Implementations should reject such inputs before any
+	/// heavy computation.
+	fn precompile_gas(&self, _address: H160, _input: &[u8]) -> Result<u64, ExitError> {
+		Ok(0)
+	}
 	/// Get valids of address.
 	fn valids(&self, address: H160) -> Vec<u8>;
+	/// Get the address an EIP-7702 delegation designator installed at
+	/// `address` points to, if any.
+	fn delegated_code(&self, address: H160) -> Option<H160> {
+		delegation_designator(&self.code(address))
+	}
 	/// Get storage value of address at index.
 	fn storage(&self, address: H160, index: U256) -> U256;
+	/// Get the storage values of `address` at `indices`, in order, so that
+	/// remote state can be read in a single round-trip. Defaults to one
+	/// `storage` call per index.
+	fn storage_batch(&self, address: H160, indices: &[U256]) -> Vec<U256> {
+		indices.iter().map(|index| self.storage(address, *index)).collect()
+	}
 
 	/// Get the gas left value.
 	fn gas_left(&self) -> U256;
 	/// Get the gas price value.
 	fn gas_price(&self) -> U256;
+	/// Get the effective gas price, returned by `GASPRICE` when the base fee
+	/// is enabled. EIP-1559 handlers can use `crate::effective_gas_price`.
+	fn effective_gas_price(&self) -> U256 {
+		self.gas_price()
+	}
 	/// Get execution origin.
 	fn origin(&self) -> H160;
-	/// Get environmental block hash.
+	/// Get environmental block hash. `BLOCKHASH` only queries the 256 blocks
+	/// preceding the current one.
 	fn block_hash(&self, number: U256) -> H256;
 	/// Get environmental block number.
 	fn block_number(&self) -> U256;
@@ -49,26 +133,134 @@ pub trait Handler {
 	fn block_timestamp(&self) -> U256;
 	/// Get environmental block difficulty.
 	fn block_difficulty(&self) -> U256;
+	/// Get environmental block number as `u64`. Defaults to `block_number`,
+	/// saturated to `u64::MAX`.
+	fn block_number_u64(&self) -> u64 {
+		let number = self.block_number();
+		if number > U256::from(u64::MAX) { u64::MAX } else { number.as_u64() }
+	}
 	/// Get environmental gas limit.
 	fn block_gas_limit(&self) -> U256;
+	/// Get environmental gas limit as `u64`. Defaults to `block_gas_limit`,
+	/// saturated to `u64::MAX`.
+	fn block_gas_limit_u64(&self) -> u64 {
+		let gas_limit = self.block_gas_limit();
+		if gas_limit > U256::from(u64::MAX) { u64::MAX } else { gas_limit.as_u64() }
+	}
 	/// Get environmental chain ID.
 	fn chain_id(&self) -> U256;
+	/// Get environmental block base fee per gas.
+	fn block_base_fee_per_gas(&self) -> U256 {
+		U256::zero()
+	}
+	/// Get all environmental block fields at once. The block opcodes read
+	/// them from here, once per frame: the runtime keeps them for the
+	/// following block opcodes. Handlers fetching them from an external
+	/// source can override this to serve them from a per-block cache.
+	fn block_env(&self) -> BlockEnv {
+		let difficulty = self.block_difficulty();
+		BlockEnv {
+			coinbase: self.block_coinbase(),
+			timestamp: self.block_timestamp(),
+			number: self.block_number(),
+			difficulty,
+			gas_limit: self.block_gas_limit_u64(),
+			chain_id: self.chain_id(),
+			base_fee: self.block_base_fee_per_gas(),
+			prev_randao: H256::from(difficulty),
+		}
+	}
+	/// Get environmental block excess blob gas.
+	fn excess_blob_gas(&self) -> u64 {
+		0
+	}
+	/// Get environmental blob base fee. Computed from `excess_blob_gas`
+	/// unless overridden.
+	fn blob_base_fee(&self) -> U256 {
+		calc_blob_base_fee(self.excess_blob_gas())
+	}
 
 	/// Set storage value of address at index.
 	fn set_storage(&mut self, address: H160, index: U256, value: U256) -> Result<(), ExitError>;
+	/// Set storage value of address at index, returning the value it
+	/// replaced. `SSTORE` writes through this. Defaults to `storage` followed
+	/// by `set_storage`; handlers that get the previous value from the write
+	/// itself can save a read.
+	fn replace_storage(&mut self, address: H160, index: U256, value: U256) -> Result<U256, ExitError> {
+		let previous = self.storage(address, index);
+		self.set_storage(address, index, value)?;
+		Ok(previous)
+	}
 	/// Create a log owned by address with given topics and data.
 	fn log(&mut self, address: H160, topcis: Vec<H256>, data: Vec<u8>) -> Result<(), ExitError>;
+	/// Number of logs emitted so far by the transaction, excluding those of
+	/// reverted frames. Only queried when `Config::max_logs` is set.
+	fn log_count(&self) -> usize {
+		0
+	}
+	/// Emit an `Event::Custom` of a chain extension to the tracer, for
+	/// instance from a precompile. Defaults to emitting it as is.
+	#[cfg(feature = "tracing")]
+	fn custom_event(&self, tag: u32, data: &[u8]) {
+		evm_core::event!(Event::Custom(CustomTrace { tag, data }));
+	}
 	/// Mark an address to be deleted, with funds transferred to target.
 	fn mark_delete(&mut self, address: H160, target: H160) -> Result<(), ExitError>;
-	/// Invoke a create operation.
+	/// Self-destruct `address`, moving `balance` out of it to `beneficiary`,
+	/// or burning it when `beneficiary` is `address` itself. The runtime
+	/// passes a zero balance for a self-beneficiary before EIP-6780, so the
+	/// balance stays. Defaults to `mark_delete`.
+	fn selfdestruct(&mut self, address: H160, beneficiary: H160, _balance: U256) -> Result<(), ExitError> {
+		self.mark_delete(address, beneficiary)
+	}
+	/// Whether `address` has self-destructed in the transaction, outside of
+	/// reverted frames. Only queried when `Config::has_selfdestruct_refund`
+	/// is set, to refund each contract once.
+	fn is_selfdestructed(&self, _address: H160) -> bool {
+		false
+	}
+	/// Whether `address` has not been accessed yet in the transaction
+	/// (EIP-2929).
+	fn is_cold_account(&self, _address: H160) -> bool {
+		false
+	}
+	/// Add `address` to the addresses accessed in the transaction (EIP-2929).
+	/// The runtime warms the address of every create once it is known, in
+	/// the creating frame.
+	fn warm_account(&mut self, _address: H160) {}
+	/// Increment the nonce of `address`, returning whether it did. Called by
+	/// `CREATE` and `CREATE2` right before `create` when
+	/// `Config::create_increase_nonce` is set. Does nothing by default, for
+	/// handlers incrementing the nonce in `create`.
+	fn inc_nonce(&mut self, _address: H160) -> Result<bool, ExitError> {
+		Ok(false)
+	}
+	/// Invoke a create operation deploying to `address`, which the runtime
+	/// derived from `scheme` before incrementing the nonce of `caller`.
 	fn create(
 		&mut self,
 		caller: H160,
 		scheme: CreateScheme,
+		address: H160,
 		value: U256,
 		init_code: Vec<u8>,
 		target_gas: Option<u64>,
 	) -> Capture<(ExitReason, Option<H160>, Vec<u8>), Self::CreateInterrupt>;
+	/// Number of create frames among the ancestors of the current frame,
+	/// itself included. Only queried when `Config::create_depth_limit` is
+	/// set.
+	fn create_depth(&self) -> usize {
+		0
+	}
+	/// Execute the create behind an interrupt returned by `create`, for
+	/// `Runtime::resolve_interrupt_sync`. Must return `Capture::Exit`; a trap
+	/// is treated as an unhandled interrupt. Unsupported by default.
+	fn create_sync(
+		&mut self,
+		_interrupt: Self::CreateInterrupt,
+	) -> Capture<(ExitReason, Option<H160>, Vec<u8>), Self::CreateInterrupt> {
+		Capture::Exit((ExitFatal::UnhandledInterrupt.into(), None, Vec::new()))
+	}
 	/// Feed in create feedback.
 	fn create_feedback(
 		&mut self,
@@ -76,7 +268,16 @@ pub trait Handler {
 	) -> Result<(), ExitError> {
 		Ok(())
 	}
-	/// Invoke a call operation.
+	/// Move the value of a `CALL` or `CALLCODE`, called by the runtime right
+	/// after `enter_frame`, once the source balance has been checked. The
+	/// transfer is part of the frame: it should be rolled back by
+	/// `exit_frame(true)`. On error the call fails without running. Does
+	/// nothing by default, for handlers moving the value in `call`.
+	fn transfer(&mut self, _transfer: &Transfer) -> Result<(), ExitError> {
+		Ok(())
+	}
+	/// Invoke a call operation. `transfer` has already been passed to
+	/// `Handler::transfer`.
 	fn call(
 		&mut self,
 		code_address: H160,
@@ -86,6 +287,15 @@ pub trait Handler {
 		is_static: bool,
 		context: Context,
 	) -> Capture<(ExitReason, Vec<u8>), Self::CallInterrupt>;
+	/// Execute the call behind an interrupt returned by `call`, for
+	/// `Runtime::resolve_interrupt_sync`. Must return `Capture::Exit`; a trap
+	/// is treated as an unhandled interrupt. Unsupported by default.
+	fn call_sync(
+		&mut self,
+		_interrupt: Self::CallInterrupt,
+	) -> Capture<(ExitReason, Vec<u8>), Self::CallInterrupt> {
+		Capture::Exit((ExitFatal::UnhandledInterrupt.into(), Vec::new()))
+	}
 	/// Feed in call feedback.
 	fn call_feedback(
 		&mut self,
@@ -94,6 +304,14 @@ pub trait Handler {
 		Ok(())
 	}
 
+	/// Called by the runtime right before a call or create frame is entered.
+	fn enter_frame(&mut self) {}
+	/// Called by the runtime once the frame entered by the matching
+	/// `enter_frame` has exited. `reverted` is `true` when the frame did not
+	/// succeed, in which case logs, storage changes and transfers made inside
+	/// it (and inside its children) should be rolled back.
+	fn exit_frame(&mut self, _reverted: bool) {}
+
 	/// Pre-validation step for the runtime.
 	fn pre_validate(
 		&mut self,
@@ -101,6 +319,30 @@ pub trait Handler {
 		opcode: Opcode,
 		stack: &Stack
 	) -> Result<(), ExitError>;
+	/// Charge gas computed by the runtime while evaluating an opcode, on top
+	/// of what `pre_validate` charged, such as the `SELFDESTRUCT` new account
+	/// surcharge.
+	fn record_cost(&mut self, _cost: u64) -> Result<(), ExitError> {
+		Ok(())
+	}
+	/// Credit a refund computed by the runtime, such as the `SELFDESTRUCT`
+	/// refund. Refunds of reverted frames should be rolled back.
+	fn record_refund(&mut self, _refund: i64) -> Result<(), ExitError> {
+		Ok(())
+	}
+	/// Compute the gas `pre_validate` would charge for `opcode` given the
+	/// current stack and memory, without charging it. Memory expansion is
+	/// left out: `Runtime::preview_gas_cost` adds it. For `CALL`-like opcodes
+	/// this is an estimate, as the gas used by the child frame is unknown.
+	/// Returns `None` if the cost cannot be previewed.
+	fn preview_gas_cost(
+		&self,
+		_context: &Context,
+		_opcode: Opcode,
+		_machine: &Machine
+	) -> Option<u64> {
+		None
+	}
 	/// Handle other unknown external opcodes.
 	fn other(
 		&mut self,
@@ -1,4 +1,5 @@
-use crate::{Runtime, Handler, ExitFatal};
+use crate::{Runtime, Handler, ExitFatal, ExitReason, Control, H160};
+use alloc::vec::Vec;
 
 /// Interrupt resolution.
 pub enum Resolve<'a, H: Handler> {
@@ -15,8 +16,31 @@ pub struct ResolveCreate<'a> {
 
 impl<'a> ResolveCreate<'a> {
 	pub(crate) fn new(runtime: &'a mut Runtime) -> Self {
+		runtime.interrupts += 1;
 		Self { runtime }
 	}
+
+	/// Resolve the interrupt with the result of the create, pushing the
+	/// created address (or zero on failure) onto the parent's stack and
+	/// keeping the return data if the create reverted. The runtime can be run
+	/// again afterwards.
+	pub fn finish<H: Handler>(
+		self,
+		reason: ExitReason,
+		address: Option<H160>,
+		return_data: Vec<u8>,
+		handler: &mut H,
+	) -> Control<H> {
+		let control = self.runtime.resume_with_create_result(reason, address, return_data, handler);
+		core::mem::forget(self);
+		control
+	}
+
+	/// Release the runtime without resolving the interrupt, for hosts that
+	/// resolve it later with `Runtime::resume_with_create_result`.
+	pub const fn defer(self) {
+		core::mem::forget(self);
+	}
 }
 
 impl<'a> Drop for ResolveCreate<'a> {
@@ -33,8 +57,29 @@ pub struct ResolveCall<'a> {
 
 impl<'a> ResolveCall<'a> {
 	pub(crate) fn new(runtime: &'a mut Runtime) -> Self {
+		runtime.interrupts += 1;
 		Self { runtime }
 	}
+
+	/// Resolve the interrupt with the result of the call, writing the return
+	/// data to the output region and pushing the success flag onto the
+	/// parent's stack. The runtime can be run again afterwards.
+	pub fn finish<H: Handler>(
+		self,
+		reason: ExitReason,
+		return_data: Vec<u8>,
+		handler: &mut H,
+	) -> Control<H> {
+		let control = self.runtime.resume_with_call_result(reason, return_data, handler);
+		core::mem::forget(self);
+		control
+	}
+
+	/// Release the runtime without resolving the interrupt, for hosts that
+	/// resolve it later with `Runtime::resume_with_call_result`.
+	pub const fn defer(self) {
+		core::mem::forget(self);
+	}
 }
 
 impl<'a> Drop for ResolveCall<'a> {
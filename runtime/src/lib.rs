@@ -1,7 +1,7 @@
 //! Runtime layer for EVM.
 
 #![deny(warnings)]
-#![forbid(unsafe_code, unused_variables)]
+#![forbid(unsafe_code, unused_variables, unused_imports)]
 #![deny(clippy::all, clippy::pedantic, clippy::nursery)]
 #![allow(
 	clippy::module_name_repetitions,
@@ -9,7 +9,6 @@
 	clippy::missing_panics_doc
 )]
 #![cfg_attr(not(feature = "std"), no_std)]
-#![cfg_attr(not(feature = "tracing"), forbid(unused_imports))]
 
 extern crate alloc;
 
@@ -17,27 +16,159 @@ extern crate alloc;
 mod eval;
 mod interrupt;
 mod handler;
+mod delegation;
+mod blob;
+mod fee;
+mod gas;
+mod code_hash;
+mod create_address;
+mod validate;
+mod serialization;
+#[cfg(feature = "std")]
+mod caching;
+#[cfg(feature = "std")]
+mod access_list;
+#[cfg(feature = "std")]
+mod valids_cache;
 
 pub use evm_core::*;
 
 pub use crate::interrupt::{Resolve, ResolveCall, ResolveCreate};
-pub use crate::handler::Handler;
+pub use crate::handler::{Handler, BlockEnv};
+#[cfg(feature = "std")]
+pub use crate::caching::CachingHandler;
+#[cfg(feature = "std")]
+pub use crate::access_list::AccessListRecorder;
+#[cfg(feature = "std")]
+pub use crate::valids_cache::ValidsCache;
+pub use crate::delegation::{DELEGATION_PREFIX, delegation_designator};
+pub use crate::fee::effective_gas_price;
+pub use crate::gas::{all_but_one_64th, memory_gas};
+pub use crate::code_hash::empty_code_hash;
+pub use crate::create_address::{create_address, create2_address};
+pub use crate::validate::ConfigError;
+pub use crate::blob::{MIN_BLOB_BASE_FEE, BLOB_BASE_FEE_UPDATE_FRACTION, fake_exponential, calc_blob_base_fee};
 pub use crate::eval::{save_return_value, save_created_address, Control};
 
 use alloc::vec::Vec;
+#[cfg(any(feature = "tracing", feature = "code-hash-cache"))]
+use alloc::collections::BTreeMap;
 
 /// EVM runtime.
 ///
 /// The runtime wraps an EVM `Machine` with support of return data and context.
+///
+/// Its borsh layout is versioned, see the `serialization` module: runtimes
+/// serialized before the versioning still deserialize.
 #[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
 #[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct Runtime {
 	machine: Machine,
 	status: Result<(), ExitReason>,
 	#[cfg_attr(feature = "with-serde", serde(with = "serde_bytes"))]
 	return_data_buffer: Vec<u8>,
 	context: Context,
+	/// Memory region, as offset and length, receiving the output of the
+	/// `CALL` awaiting its result. `None` for a `CALL` suspended by a
+	/// runtime serialized in the legacy layout, whose output region is
+	/// still on the stack.
+	#[cfg_attr(feature = "with-serde", serde(default))]
+	call_output: Option<(usize, usize)>,
+	/// Number of call frames spawned by this frame. It is not serialized.
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	call_frames: u64,
+	/// Number of create frames spawned by this frame. It is not serialized.
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	create_frames: u64,
+	/// Number of interrupts `run` returned. It is not serialized.
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	interrupts: u64,
+	/// Gas charged so far for the opcode being executed, checked against
+	/// `Config::per_opcode_gas_cap`. Only counted when a cap is set.
+	#[cfg_attr(feature = "with-serde", serde(default))]
+	opcode_gas: u64,
+	/// Block fields, read through `Handler::block_env` by the first block
+	/// opcode of the frame. It is not serialized.
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	block_env: Option<BlockEnv>,
+	/// Configuration. It is not serialized: a deserialized runtime has none
+	/// until `set_config` restores it, and refuses to run meanwhile.
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	config: Option<&'static Config>,
+	/// Storage written by this frame, as original and current values. It is
+	/// not serialized.
+	#[cfg(feature = "tracing")]
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	storage_writes: BTreeMap<U256, (U256, U256)>,
+	/// Gas accounting of this frame for the `FrameGas` event. It is not
+	/// serialized.
+	#[cfg(feature = "tracing")]
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	frame_gas: FrameGas,
+	/// `EXTCODEHASH` results of this frame, cleared whenever a child frame
+	/// returns. It is not serialized.
+	#[cfg(feature = "code-hash-cache")]
+	#[cfg_attr(feature = "with-codec", codec(skip))]
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	code_hashes: BTreeMap<H160, H256>,
+}
+
+/// Whether `opcode` spawns a call or create frame.
+#[cfg(any(debug_assertions, feature = "tracing"))]
+const fn is_frame_opcode(opcode: Opcode) -> bool {
+	matches!(
+		opcode,
+		Opcode::CALL | Opcode::CALLCODE | Opcode::DELEGATECALL |
+		Opcode::STATICCALL | Opcode::CREATE | Opcode::CREATE2
+	)
+}
+
+/// Start counting the gas of an opcode from what `Handler::pre_validate`
+/// charged it, `OutOfGas` if that already exceeds `cap`.
+fn start_opcode_gas(cap: u64, before: U256, after: U256, opcode_gas: &mut u64) -> Result<(), ExitError> {
+	let charged = before.saturating_sub(after);
+	if charged > U256::from(cap) {
+		return Err(ExitError::OutOfGas);
+	}
+	*opcode_gas = charged.as_u64();
+	Ok(())
+}
+
+/// Gas of a frame, for the `FrameGas` event.
+#[cfg(feature = "tracing")]
+#[derive(Clone, Copy, Debug, Default)]
+struct FrameGas {
+	/// Call depth reported in the event.
+	depth: usize,
+	/// `Handler::gas_left` when the frame first ran.
+	entry: Option<U256>,
+	/// Gas used by the children that have returned.
+	children: u64,
+	/// `Handler::gas_left` before the call or create awaiting its result.
+	pending: Option<U256>,
+}
+
+/// Gas used between two `Handler::gas_left` readings, saturated to `u64`.
+#[cfg(feature = "tracing")]
+fn gas_used_between(before: U256, after: U256) -> u64 {
+	let used = before.saturating_sub(after);
+	if used > U256::from(u64::MAX) { u64::MAX } else { used.as_u64() }
+}
+
+/// Jump destination bitmap whose length does not match the code it was
+/// passed with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValidsLengthError {
+	/// `Valids::size_needed` of the code length.
+	pub expected: usize,
+	pub actual: usize,
 }
 
 impl Runtime {
@@ -48,11 +179,233 @@ impl Runtime {
 		data: Vec<u8>,
 		context: Context,
 	) -> Self {
+		Self::new_with_config(code, valids, data, context, &CONFIG)
+	}
+
+	/// Create a new runtime whose machine starts with `stack`, from bottom to
+	/// top, and with `memory` at offset zero, to test opcodes without running
+	/// code to set them up. Testing aid only. Panics if `stack` or `memory`
+	/// exceed their limit.
+	#[cfg(feature = "test-utils")]
+	#[must_use]
+	pub fn with_state(
+		code: Vec<u8>,
+		valids: Vec<u8>,
+		data: Vec<u8>,
+		context: Context,
+		stack: Vec<U256>,
+		memory: &[u8],
+	) -> Self {
+		let mut runtime = Self::new(code, valids, data, context);
+		for value in stack {
+			runtime.machine.stack_mut().push_u256(value).expect("stack over the stack limit");
+		}
+		if !memory.is_empty() {
+			let machine_memory = runtime.machine.memory_mut();
+			machine_memory.resize_offset(0, memory.len()).expect("memory over the memory limit");
+			machine_memory.set(0, memory, None).expect("memory over the memory limit");
+		}
+		runtime
+	}
+
+	/// Create a new runtime with given code and data, checking that `valids`
+	/// is a bitmap of the right size for `code`.
+	pub fn try_new(
+		code: Vec<u8>,
+		valids: Vec<u8>,
+		data: Vec<u8>,
+		context: Context,
+	) -> Result<Self, ValidsLengthError> {
+		Self::try_new_with_config(code, valids, data, context, &CONFIG)
+	}
+
+	/// Create a new runtime with given code, data and configuration, checking
+	/// that `valids` is a bitmap of the right size for `code`.
+	pub fn try_new_with_config(
+		code: Vec<u8>,
+		valids: Vec<u8>,
+		data: Vec<u8>,
+		context: Context,
+		config: &'static Config,
+	) -> Result<Self, ValidsLengthError> {
+		let expected = Valids::size_needed(code.len());
+		if valids.len() != expected {
+			return Err(ValidsLengthError { expected, actual: valids.len() });
+		}
+		Ok(Self::new_with_config(code, valids, data, context, config))
+	}
+
+	/// Create a new runtime with given code, data and configuration. A
+	/// `valids` bitmap shorter than the code makes the uncovered jump
+	/// destinations invalid; see `try_new_with_config`.
+	#[must_use]
+	pub fn new_with_config(
+		code: Vec<u8>,
+		valids: Vec<u8>,
+		data: Vec<u8>,
+		context: Context,
+		config: &'static Config,
+	) -> Self {
+		let machine = Machine::new(code, valids, data, config.stack_limit, config.memory_limit);
+		let mut runtime = Self::from_parts(machine, Ok(()), Vec::new(), context);
+		runtime.set_config(config);
+		runtime
+	}
+
+	/// Runtime of `machine`, without configuration and with nothing
+	/// pending.
+	const fn from_parts(machine: Machine, status: Result<(), ExitReason>, return_data_buffer: Vec<u8>, context: Context) -> Self {
 		Self {
-			machine: Machine::new(code, valids, data, CONFIG.stack_limit, CONFIG.memory_limit),
-			status: Ok(()),
-			return_data_buffer: Vec::new(),
+			machine,
+			status,
+			return_data_buffer,
 			context,
+			call_output: None,
+			call_frames: 0,
+			create_frames: 0,
+			interrupts: 0,
+			opcode_gas: 0,
+			block_env: None,
+			config: None,
+			#[cfg(feature = "tracing")]
+			storage_writes: BTreeMap::new(),
+			#[cfg(feature = "tracing")]
+			frame_gas: FrameGas { depth: 0, entry: None, children: 0, pending: None },
+			#[cfg(feature = "code-hash-cache")]
+			code_hashes: BTreeMap::new(),
+		}
+	}
+
+	/// Create the runtime of the top-level frame of a transaction from
+	/// `caller` to `to`, running `code`, the code of `to`, with `data` as
+	/// input.
+	#[must_use]
+	pub fn from_transaction(
+		caller: H160,
+		to: H160,
+		value: U256,
+		data: Vec<u8>,
+		code: Vec<u8>,
+		config: &'static Config,
+	) -> Self {
+		let valids = Valids::compute(&code);
+		let context = Context { address: to, caller, apparent_value: value };
+		Self::new_with_config(code, valids, data, context, config)
+	}
+
+	/// Create the runtime of the top-level frame of a contract creation
+	/// transaction from `caller`, running `init_code` at `address`, the
+	/// address of the new contract.
+	#[must_use]
+	pub fn from_create_transaction(
+		caller: H160,
+		address: H160,
+		value: U256,
+		init_code: Vec<u8>,
+		config: &'static Config,
+	) -> Self {
+		Self::from_transaction(caller, address, value, Vec::new(), init_code, config)
+	}
+
+	/// Create a new runtime with given code, data and configuration, taking
+	/// the jump destination bitmap from `cache`. `code_hash` must be the
+	/// Keccak-256 hash of `code`, as known to the handler.
+	#[cfg(feature = "std")]
+	#[must_use]
+	pub fn new_cached(
+		code: Vec<u8>,
+		code_hash: H256,
+		data: Vec<u8>,
+		context: Context,
+		config: &'static Config,
+		cache: &mut ValidsCache,
+	) -> Self {
+		let valids = cache.get_or_compute(code_hash, &code).to_vec();
+		Self::new_with_config(code, valids, data, context, config)
+	}
+
+	/// Wall-clock nanoseconds spent per opcode, indexed by opcode byte. Calls
+	/// and creates include the nested frames the handler runs synchronously.
+	#[cfg(feature = "profiling")]
+	#[must_use]
+	pub const fn opcode_timings(&self) -> &[u64; 256] {
+		self.machine.opcode_timings().as_array()
+	}
+
+	/// Zero the opcode timings.
+	#[cfg(feature = "profiling")]
+	pub const fn reset_opcode_timings(&mut self) {
+		self.machine.opcode_timings_mut().reset();
+	}
+
+	/// Number of call frames this frame spawned, whether the handler ran them
+	/// synchronously or returned an interrupt. Calls failing before reaching
+	/// `Handler::call` are not counted. Diagnostic only: a deserialized
+	/// runtime counts from zero.
+	#[must_use]
+	pub const fn call_frame_count(&self) -> u64 {
+		self.call_frames
+	}
+
+	/// Number of create frames this frame spawned, whether the handler ran
+	/// them synchronously or returned an interrupt. Creates failing before
+	/// reaching `Handler::create` are not counted. Diagnostic only: a
+	/// deserialized runtime counts from zero.
+	#[must_use]
+	pub const fn create_frame_count(&self) -> u64 {
+		self.create_frames
+	}
+
+	/// Number of call and create frames this frame spawned.
+	#[must_use]
+	pub const fn frames_executed(&self) -> u64 {
+		self.call_frames + self.create_frames
+	}
+
+	/// Number of times `run` returned an interrupt for the host to resolve.
+	/// Unlike the frame counts, frames the handler runs synchronously are
+	/// not counted. Diagnostic only: a deserialized runtime counts from
+	/// zero.
+	#[must_use]
+	pub const fn interrupt_count(&self) -> u64 {
+		self.interrupts
+	}
+
+	/// Get the runtime configuration, `None` for a deserialized runtime
+	/// until `set_config` is called.
+	#[must_use]
+	pub const fn config(&self) -> Option<&'static Config> {
+		self.config
+	}
+
+	/// Restore the configuration of a deserialized runtime, which must be
+	/// the one it was created with: neither the configuration nor the
+	/// machine settings taken from it, such as `Config::opcode_aliases`,
+	/// are serialized.
+	pub const fn set_config(&mut self, config: &'static Config) {
+		self.config = Some(config);
+		self.machine.set_opcode_aliases(config.opcode_aliases);
+		self.machine.set_max_backward_jumps_per_dest(config.max_backward_jumps_per_dest);
+		#[cfg(feature = "debug-features")]
+		self.machine.set_ignore_revert(config.ignore_revert);
+	}
+
+	/// Whether the runtime can run: it has not exited and has a
+	/// configuration.
+	const fn runnable(&self) -> Result<(), ExitReason> {
+		match (self.status, self.config) {
+			(Err(e), _) => Err(e),
+			(Ok(()), None) => Err(ExitReason::Fatal(ExitFatal::ConfigNotSet)),
+			(Ok(()), Some(_)) => Ok(()),
+		}
+	}
+
+	/// Configuration of a runtime that is running or being resumed, both of
+	/// which require one.
+	pub(crate) const fn cfg(&self) -> &'static Config {
+		match self.config {
+			Some(config) => config,
+			None => panic!("runtime configuration not restored, see Runtime::set_config"),
 		}
 	}
 
@@ -66,27 +419,350 @@ impl Runtime {
 		self.return_data_buffer = data;
 	}
 
+	/// Instructions of the code as `(position, opcode, immediate)`. See
+	/// `Machine::disassemble`.
+	#[must_use]
+	pub fn disassemble(&self) -> Vec<(usize, Opcode, Option<Vec<u8>>)> {
+		self.machine.disassemble()
+	}
+
 	/// Get a reference to the machine.
 	pub fn machine(&self) -> &Machine {
 		&self.machine
 	}
 
-	/// Loop stepping the runtime until it stops.
+	/// Get a mutable reference to the machine, for embedders building on
+	/// top of the runtime, such as precompiles working on the caller's
+	/// memory. Nothing is checked: changes must keep the machine in a state
+	/// the code could have reached. In particular, callers must not move
+	/// the program counter off an instruction boundary or a valid jump
+	/// destination, must not change the code or its jump destinations, and
+	/// must not touch the stack or memory of a runtime awaiting the result
+	/// of a call, whose operands were already consumed.
+	#[cfg(feature = "advanced-api")]
+	pub const fn machine_mut(&mut self) -> &mut Machine {
+		&mut self.machine
+	}
+
+	/// Get the input data of this frame.
+	#[must_use]
+	pub fn input_data(&self) -> &[u8] {
+		self.machine.data()
+	}
+
+	/// Peak memory size, in bytes, reached by this frame. Memory is never
+	/// shrunk by `resize_offset`, so the effective length is its high-water
+	/// mark, starting from zero for every new runtime.
+	#[must_use]
+	pub const fn memory_high_water_mark(&self) -> usize {
+		self.machine.memory().effective_len()
+	}
+
+	/// Size of the memory in 32-byte words, as charged by `memory_gas`.
+	#[must_use]
+	pub const fn memory_words(&self) -> usize {
+		self.machine.memory().effective_len().div_ceil(32)
+	}
+
+	/// Memory expansion gas of the frame so far, that is `memory_gas` of
+	/// `memory_words`. After a run, this is the total memory gas the frame
+	/// was charged.
+	#[must_use]
+	pub fn total_memory_gas(&self) -> u64 {
+		memory_gas(self.memory_words())
+	}
+
+	/// Clear an `OutOfGas` error raised by the handler's `pre_validate`, so
+	/// that running again retries the rejected opcode once the handler's gas
+	/// has been replenished. This is meant for debugging and what-if
+	/// analysis. Any other exit, including `OutOfGas` raised while an opcode
+	/// was executing, cannot be cleared and returns `Err(())`.
+	#[allow(clippy::result_unit_err)]
+	pub fn clear_error(&mut self) -> Result<(), ()> {
+		if self.status != Err(ExitError::OutOfGas.into()) || !self.machine.resume() {
+			return Err(());
+		}
+
+		self.status = Ok(());
+		Ok(())
+	}
+
+	/// Preview the gas `opcode` would cost if executed on the current state:
+	/// the handler's `preview_gas_cost` plus the memory expansion, computed
+	/// from the operands on the stack. Returns `None` if the handler cannot
+	/// preview it or the operands are missing. Neither the runtime nor the
+	/// handler is modified.
+	pub fn preview_gas_cost<H: Handler>(&self, opcode: Opcode, handler: &H) -> Option<u64> {
+		let memory = gas::memory_expansion_gas(opcode, self.machine.stack(), self.memory_words()).ok()?;
+		let cost = handler.preview_gas_cost(&self.context, opcode, &self.machine)?;
+		Some(cost.saturating_add(memory))
+	}
+
+	/// Resolve a call interrupt with the result of the call, writing the
+	/// return data to the output region and pushing the success flag onto the
+	/// stack. The runtime can be run again afterwards. Must only be called
+	/// while the runtime is waiting on a call interrupt.
+	/// A deserialized runtime whose configuration has not been restored
+	/// exits with `ExitFatal::ConfigNotSet` and is left unchanged.
+	pub fn resume_with_call_result<H: Handler>(
+		&mut self,
+		reason: ExitReason,
+		return_data: Vec<u8>,
+		handler: &mut H,
+	) -> Control<H> {
+		if self.config.is_none() {
+			return Control::Exit(ExitFatal::ConfigNotSet.into());
+		}
+		let control = save_return_value(self, reason, return_data, handler);
+		#[cfg(feature = "tracing")]
+		self.record_child_gas(handler);
+		self.apply(control, handler)
+	}
+
+	/// Resolve a create interrupt with the result of the create, pushing the
+	/// created address (or zero on failure) onto the stack. The return data
+	/// is kept as the revert reason if the create reverted. The runtime can
+	/// be run again afterwards. Must only be called while the runtime is
+	/// waiting on a create interrupt.
+	/// A deserialized runtime whose configuration has not been restored
+	/// exits with `ExitFatal::ConfigNotSet` and is left unchanged.
+	pub fn resume_with_create_result<H: Handler>(
+		&mut self,
+		reason: ExitReason,
+		address: Option<H160>,
+		return_data: Vec<u8>,
+		handler: &mut H,
+	) -> Control<H> {
+		if self.config.is_none() {
+			return Control::Exit(ExitFatal::ConfigNotSet.into());
+		}
+		let control = save_created_address(self, reason, address, return_data, handler);
+		#[cfg(feature = "tracing")]
+		self.record_child_gas(handler);
+		self.apply(control, handler)
+	}
+
+	/// Resolve an interrupt by running the call or create behind it through
+	/// `Handler::call_sync` or `Handler::create_sync`, then resuming the
+	/// interrupted runtime with the result. The handler must return
+	/// `Capture::Exit` from those; a nested trap stops the runtime with
+	/// `ExitFatal::UnhandledInterrupt`. The runtime can be run again
+	/// afterwards.
+	pub fn resolve_interrupt_sync<H: Handler>(resolve: Resolve<'_, H>, handler: &mut H) -> Control<H> {
+		match resolve {
+			Resolve::Call(interrupt, resolve) => {
+				let (reason, return_data) = match handler.call_sync(interrupt) {
+					Capture::Exit(result) => result,
+					Capture::Trap(_) => (ExitFatal::UnhandledInterrupt.into(), Vec::new()),
+				};
+				resolve.finish(reason, return_data, handler)
+			},
+			Resolve::Create(interrupt, resolve) => {
+				let (reason, address, return_data) = match handler.create_sync(interrupt) {
+					Capture::Exit(result) => result,
+					Capture::Trap(_) => (ExitFatal::UnhandledInterrupt.into(), None, Vec::new()),
+				};
+				resolve.finish(reason, address, return_data, handler)
+			},
+		}
+	}
+
+	/// Stop the runtime with `reason`, typically `ExitFatal::Killed`, from
+	/// outside of its execution. Further runs return `reason`.
+	pub fn halt(&mut self, reason: ExitReason) {
+		self.machine.exit(reason);
+		self.status = Err(reason);
+		#[cfg(feature = "tracing")]
+		self.trace_storage_diff(reason);
+	}
+
+	/// Apply the control returned by a `save_*` function, so that a fatal
+	/// child exit also stops this runtime.
+	fn apply<H: Handler>(&mut self, control: Control<H>, handler: &H) -> Control<H> {
+		if let Control::Exit(reason) = control {
+			self.machine.exit(reason);
+			self.status = Err(reason);
+			#[cfg(feature = "tracing")]
+			self.trace_storage_diff(reason);
+			#[cfg(feature = "tracing")]
+			self.trace_frame_gas(handler);
+		}
+		#[cfg(not(feature = "tracing"))]
+		let _ = handler;
+		control
+	}
+
+	/// Set the call depth reported by the `FrameGas` event of this frame,
+	/// zero by default.
+	#[cfg(feature = "tracing")]
+	pub const fn set_depth(&mut self, depth: usize) {
+		self.frame_gas.depth = depth;
+	}
+
+	/// Count the gas used by the child frame that has just returned.
+	#[cfg(feature = "tracing")]
+	fn record_child_gas<H: Handler>(&mut self, handler: &H) {
+		if let Some(before) = self.frame_gas.pending.take() {
+			let used = gas_used_between(before, handler.gas_left());
+			self.frame_gas.children = self.frame_gas.children.saturating_add(used);
+		}
+	}
+
+	/// Emit the gas used by this frame once it has exited. Nothing is
+	/// emitted for a frame that never ran.
+	#[cfg(feature = "tracing")]
+	fn trace_frame_gas<H: Handler>(&self, handler: &H) {
+		if let Some(entry) = self.frame_gas.entry {
+			event!(Event::FrameGas(FrameGasTrace {
+				depth: self.frame_gas.depth,
+				gas_used: gas_used_between(entry, handler.gas_left()),
+				gas_used_children: self.frame_gas.children,
+			}));
+		}
+	}
+
+	/// Record a storage write of this frame for the `StorageDiff` event.
+	#[cfg(feature = "tracing")]
+	pub(crate) fn record_storage_write(&mut self, index: U256, original: U256, value: U256) {
+		self.storage_writes.entry(index).or_insert((original, value)).1 = value;
+	}
+
+	/// Emit the net storage changes of this frame once it has exited.
+	#[cfg(feature = "tracing")]
+	fn trace_storage_diff(&mut self, reason: ExitReason) {
+		let writes = core::mem::take(&mut self.storage_writes);
+		let changes = if reason.is_succeed() {
+			writes.into_iter()
+				.filter(|(_, (original, value))| original != value)
+				.map(|(index, (original, value))| (index, original, value))
+				.collect()
+		} else {
+			Vec::new()
+		};
+
+		event!(Event::StorageDiff(StorageDiffTrace { address: self.context.address, changes }));
+	}
+
+	/// Pass the sender and recipient of a transaction whose top-level frame
+	/// is this runtime, followed by the accounts and slots of its
+	/// `access_list`, to `Handler::prefetch`, then read the slots of every
+	/// account with one `Handler::storage_batch`. Meant to be called before
+	/// the first `run`.
+	pub fn prefetch<H: Handler>(&self, handler: &H, access_list: &[(H160, Vec<H256>)]) {
+		let mut addresses = Vec::new();
+		let mut slots = Vec::new();
+		let accounts = [self.context.caller, self.context.address];
+		for address in accounts.iter().copied().chain(access_list.iter().map(|(address, _)| *address)) {
+			if !addresses.contains(&address) {
+				addresses.push(address);
+			}
+		}
+		for (address, keys) in access_list {
+			slots.extend(keys.iter().map(|key| (*address, *key)));
+		}
+		handler.prefetch(&addresses, &slots);
+		for (address, keys) in access_list {
+			if !keys.is_empty() {
+				let indices: Vec<U256> = keys.iter().map(|key| U256::from_big_endian_fast(key.as_bytes())).collect();
+				handler.storage_batch(*address, &indices);
+			}
+		}
+	}
+
+	/// Refund granted for `gas_used` and the accumulated `refund`, which is
+	/// clamped to zero if negative and capped by `Config::max_refund_quotient`.
+	const fn capped_refund(config: &Config, gas_used: u64, refund: i64) -> u64 {
+		let refund = if refund < 0 { 0 } else { refund.unsigned_abs() };
+		let cap = match gas_used.checked_div(config.max_refund_quotient) {
+			Some(cap) => cap,
+			None => 0,
+		};
+		if refund < cap { refund } else { cap }
+	}
+
+	/// Net gas charged for a transaction whose top-level frame is this
+	/// runtime, given the gas it used and the refund it accumulated. A
+	/// negative refund (EIP-1283) counts as zero, and the refund is capped to
+	/// `gas_used / Config::max_refund_quotient`. Fails with
+	/// `ExitFatal::ConfigNotSet` if the runtime was deserialized and its
+	/// configuration not restored.
+	pub const fn finalize_gas(&self, gas_used: u64, refund: i64) -> Result<u64, ExitFatal> {
+		match self.config {
+			Some(config) => Ok(gas_used - Self::capped_refund(config, gas_used, refund)),
+			None => Err(ExitFatal::ConfigNotSet),
+		}
+	}
+
+	/// Emit the `TransactionResult` event of a transaction whose top-level
+	/// frame is this runtime, given the gas it used and the refund it
+	/// accumulated, capped as by `finalize_gas`. Does nothing if the runtime
+	/// has not exited, or has no configuration.
+	#[cfg(feature = "tracing")]
+	pub fn trace_transaction_result(&self, gas_used: u64, refund: i64) {
+		if let (Err(exit_reason), Some(config)) = (self.status, self.config) {
+			let gas_refunded = Self::capped_refund(config, gas_used, refund);
+
+			event!(Event::TransactionResult(TransactionResultTrace {
+				gas_used,
+				gas_refunded,
+				gas_used_after_refund: gas_used - gas_refunded,
+				exit_reason,
+			}));
+		}
+	}
+
+	/// Loop stepping the runtime until it stops. A deserialized runtime
+	/// whose configuration has not been restored exits with
+	/// `ExitFatal::ConfigNotSet` without running.
 	pub fn run<'a, H: Handler>(
 		&'a mut self,
 		max_steps: u64,
 		handler: &mut H,
 	) -> (u64, Capture<ExitReason, Resolve<'a, H>>) {
-		if let Err(e) = self.status {
+		if let Err(e) = self.runnable() {
 			return (0, Capture::Exit(e));
 		}
 
+		#[cfg(feature = "tracing")]
+		if self.frame_gas.entry.is_none() {
+			self.frame_gas.entry = Some(handler.gas_left());
+		}
+
 		let mut steps = 0_u64;
+		// Gas left at the previous step, checked not to increase in estimate
+		// mode. Calls and creates return the unused gas of their child frame.
+		#[cfg(debug_assertions)]
+		let mut last_gas_left: Option<U256> = None;
 
 		while steps < max_steps {
 			let (steps_executed, capture) = {
 				let context = &self.context;
-				let pre_validate = |opcode, stack: &Stack| { handler.pre_validate(context, opcode, stack) };
+				let ignore_gas = self.cfg().ignore_gas;
+				let per_opcode_gas_cap = self.cfg().per_opcode_gas_cap;
+				#[cfg(debug_assertions)]
+				let estimate = self.cfg().estimate;
+				#[cfg(debug_assertions)]
+				let last_gas_left = &mut last_gas_left;
+				let opcode_gas = &mut self.opcode_gas;
+				let pre_validate = |opcode, stack: &Stack| {
+					#[cfg(debug_assertions)]
+					if estimate {
+						let gas_left = handler.gas_left();
+						if let Some(last) = last_gas_left.replace(gas_left) {
+							assert!(
+								gas_left <= last,
+								"gas left increased from {} to {} before {:?}",
+								last, gas_left, opcode,
+							);
+						}
+					}
+					let gas_before = per_opcode_gas_cap.map(|cap| (cap, handler.gas_left()));
+					match handler.pre_validate(context, opcode, stack) {
+						Err(ExitError::OutOfGas) if ignore_gas => (),
+						result => result?,
+					}
+					gas_before.map_or(Ok(()), |(cap, before)| start_opcode_gas(cap, before, handler.gas_left(), opcode_gas))
+				};
 				self.machine.run(max_steps - steps, pre_validate, &self.context)
 			};
 			steps += steps_executed;
@@ -97,10 +773,32 @@ impl Runtime {
 				},
 				Capture::Exit(reason) => {
 					self.status = Err(reason);
+					#[cfg(feature = "tracing")]
+					self.trace_storage_diff(reason);
+					#[cfg(feature = "tracing")]
+					self.trace_frame_gas(handler);
 					return (steps, Capture::Exit(reason));
 				},
 				Capture::Trap(opcode) => {
-					match eval::eval(self, opcode, handler) {
+					#[cfg(feature = "profiling")]
+					let started = std::time::Instant::now();
+					#[cfg(feature = "tracing")]
+					if is_frame_opcode(opcode) {
+						self.frame_gas.pending = Some(handler.gas_left());
+					}
+					let control = eval::eval(self, opcode, handler);
+					#[cfg(feature = "tracing")]
+					if !matches!(control, eval::Control::CallInterrupt(_) | eval::Control::CreateInterrupt(_)) {
+						self.record_child_gas(handler);
+					}
+					#[cfg(feature = "profiling")]
+					self.machine.opcode_timings_mut().record(opcode, started.elapsed());
+					#[cfg(debug_assertions)]
+					if is_frame_opcode(opcode) {
+						last_gas_left = None;
+					}
+
+					match control {
 						eval::Control::Continue => {},
 						eval::Control::CallInterrupt(interrupt) => {
 							let resolve = ResolveCall::new(self);
@@ -113,6 +811,10 @@ impl Runtime {
 						eval::Control::Exit(exit) => {
 							self.machine.exit(exit);
 							self.status = Err(exit);
+							#[cfg(feature = "tracing")]
+							self.trace_storage_diff(exit);
+							#[cfg(feature = "tracing")]
+							self.trace_frame_gas(handler);
 							return (steps, Capture::Exit(exit));
 						},
 					}
@@ -137,10 +839,31 @@ pub struct Config {
 	pub gas_sstore_reset: u64,
 	/// Gas paid for sstore refund.
 	pub refund_sstore_clears: i64,
+	/// The refund of a transaction is capped to the gas used divided by this
+	/// quotient: 2 before London, 5 since (EIP-3529). Zero disables refunds.
+	pub max_refund_quotient: u64,
+	/// Has the refund of the first `SELFDESTRUCT` of a contract in a
+	/// transaction, removed in London (EIP-3529).
+	pub has_selfdestruct_refund: bool,
 	/// Gas paid for BALANCE opcode.
 	pub gas_balance: u64,
 	/// Gas paid for SLOAD opcode.
 	pub gas_sload: u64,
+	/// Gas paid for SLOAD of a cold slot (EIP-2929).
+	pub gas_sload_cold: u64,
+	/// Gas paid for accessing a warm account or slot (EIP-2929).
+	pub gas_storage_read_warm: u64,
+	/// Gas paid for accessing a cold account (EIP-2929).
+	pub gas_account_access_cold: u64,
+	/// Gas paid per address of a transaction access list (EIP-2930).
+	pub gas_access_list_address: u64,
+	/// Gas paid per storage key of a transaction access list (EIP-2930).
+	pub gas_access_list_storage_key: u64,
+	/// Whether accounts and slots are charged as cold on first access in a
+	/// transaction and warm afterwards (EIP-2929), as tracked through
+	/// `Handler::is_cold_account`. The runtime only warms created
+	/// addresses; handlers charge the access costs in `pre_validate`.
+	pub increase_state_access_gas: bool,
 	/// Gas paid for SUICIDE opcode.
 	pub gas_suicide: u64,
 	/// Gas paid for SUICIDE opcode when it hits a new account.
@@ -149,6 +872,10 @@ pub struct Config {
 	pub gas_call: u64,
 	/// Gas paid for EXP opcode for every byte.
 	pub gas_expbyte: u64,
+	/// Gas paid per byte of code deployed by a successful create, whether
+	/// the handler executes the create synchronously or through an
+	/// interrupt.
+	pub gas_code_deposit: u64,
 	/// Gas paid for a contract creation transaction.
 	pub gas_transaction_create: u64,
 	/// Gas paid for a message call transaction.
@@ -171,14 +898,35 @@ pub struct Config {
 	pub empty_considered_exists: bool,
 	/// Whether create transactions and create opcode increases nonce by one.
 	pub create_increase_nonce: bool,
+	/// Whether a create whose address has a nonce or code, as reported by
+	/// `Handler::account_exists_for_create`, fails with `CreateCollision`
+	/// without reaching `Handler::create` (EIP-684).
+	pub has_create_collision_check: bool,
 	/// Stack limit.
 	pub stack_limit: usize,
 	/// Memory limit.
 	pub memory_limit: usize,
 	/// Call limit.
 	pub call_stack_limit: usize,
-	/// Create contract limit.
+	/// Maximum number of nested create frames, as reported by
+	/// `Handler::create_depth`.
+	pub create_depth_limit: Option<usize>,
+	/// Maximum size of the code deployed by a create (EIP-170), `None` for
+	/// no limit. Like `gas_code_deposit`, also checked for creates resolved
+	/// through an interrupt.
 	pub create_contract_limit: Option<usize>,
+	/// Maximum size of code loaded from an existing account. Larger code is
+	/// considered corrupt and execution stops with a fatal error.
+	pub max_code_size: Option<usize>,
+	/// Maximum number of logs of a transaction, as reported by
+	/// `Handler::log_count`. The frame emitting one more fails with
+	/// `ExitError::TooManyLogs`.
+	pub max_logs: Option<usize>,
+	/// Maximum size of the data returned by a child frame. The return data
+	/// buffer is replaced by every call and create, never appended to, so
+	/// this bounds its size. A child returning more fails with
+	/// `ExitError::ReturnDataLimit` and its changes are reverted.
+	pub max_return_data: Option<usize>,
 	/// Call stipend.
 	pub call_stipend: u64,
 	/// Has delegate call.
@@ -197,8 +945,47 @@ pub struct Config {
 	pub has_self_balance: bool,
 	/// Has ext code hash.
 	pub has_ext_code_hash: bool,
-	/// Whether the gasometer is running in estimate mode.
+	/// Whether EXTCODEHASH of a precompile, as reported by
+	/// `Handler::is_precompile`, is the empty code hash.
+	pub precompile_has_empty_code_hash: bool,
+	/// Has EIP-7702 delegated code execution.
+	pub has_eip7702: bool,
+	/// Has SELFDESTRUCT only deleting contracts created in the same
+	/// transaction (EIP-6780), burning the balance of a deleted contract
+	/// that names itself as beneficiary.
+	pub has_eip6780: bool,
+	/// Has base fee (EIP-1559) and the `BASEFEE` opcode (EIP-3198). Without
+	/// it, `BASEFEE` is left to `Handler::other` like any unassigned opcode.
+	pub has_base_fee: bool,
+	/// Has blob base fee (EIP-7516).
+	pub has_blob_base_fee: bool,
+	/// Bytes dispatched as another opcode, applied before decoding each
+	/// byte. For experimenting with custom opcodes only: any alias breaks
+	/// mainnet compatibility.
+	pub opcode_aliases: &'static [(u8, Opcode)],
+	/// Whether the gasometer is running in estimate mode. Debug builds then
+	/// panic if `Handler::gas_left` increases between two steps, other than
+	/// by a call or create returning unused gas.
 	pub estimate: bool,
+	/// Whether to run with unlimited gas: `OutOfGas` from
+	/// `Handler::pre_validate` and `Handler::record_cost` is ignored, while
+	/// other errors, step, stack and memory limits still apply.
+	pub ignore_gas: bool,
+	/// Most gas a single opcode may be charged: what `Handler::pre_validate`
+	/// charges, measured through `Handler::gas_left`, plus the surcharges
+	/// the runtime records while evaluating it, such as the code deposit of
+	/// a create. An opcode charged more fails with `OutOfGas`. For
+	/// sandboxing untrusted code.
+	pub per_opcode_gas_cap: Option<u64>,
+	/// Most times a single jump destination may be jumped back to before
+	/// the frame fails with `LoopDetected`. A heuristic guard against
+	/// infinite loops, tighter than the step limit.
+	pub max_backward_jumps_per_dest: Option<u64>,
+	/// Whether `REVERT` continues with the next opcode instead of exiting
+	/// the frame. Unsafe, for post-mortem debugging only: nothing is rolled
+	/// back and the execution no longer matches the chain.
+	#[cfg(feature = "debug-features")]
+	pub ignore_revert: bool,
 }
 
 pub const CONFIG: Config = Config::istanbul();
@@ -211,13 +998,22 @@ impl Config {
 			gas_ext_code_hash: 20,
 			gas_balance: 20,
 			gas_sload: 50,
+			gas_sload_cold: 0,
+			gas_storage_read_warm: 0,
+			gas_account_access_cold: 0,
+			gas_access_list_address: 0,
+			gas_access_list_storage_key: 0,
+			increase_state_access_gas: false,
 			gas_sstore_set: 20000,
 			gas_sstore_reset: 5000,
 			refund_sstore_clears: 15000,
+			max_refund_quotient: 2,
+			has_selfdestruct_refund: true,
 			gas_suicide: 0,
 			gas_suicide_new_account: 0,
 			gas_call: 40,
 			gas_expbyte: 10,
+			gas_code_deposit: 200,
 			gas_transaction_create: 21000,
 			gas_transaction_call: 21000,
 			gas_transaction_zero_data: 4,
@@ -227,11 +1023,16 @@ impl Config {
 			err_on_call_with_more_gas: true,
 			empty_considered_exists: true,
 			create_increase_nonce: false,
+			has_create_collision_check: true,
 			call_l64_after_gas: false,
 			stack_limit: 1024,
 			memory_limit: usize::max_value(),
 			call_stack_limit: 1024,
+			create_depth_limit: None,
 			create_contract_limit: None,
+			max_code_size: None,
+			max_logs: None,
+			max_return_data: None,
 			call_stipend: 2300,
 			has_delegate_call: false,
 			has_create2: false,
@@ -241,7 +1042,18 @@ impl Config {
 			has_chain_id: false,
 			has_self_balance: false,
 			has_ext_code_hash: false,
+			precompile_has_empty_code_hash: false,
+			has_eip7702: false,
+			has_eip6780: false,
+			has_base_fee: false,
+			has_blob_base_fee: false,
+			opcode_aliases: &[],
 			estimate: false,
+			ignore_gas: false,
+			per_opcode_gas_cap: None,
+			max_backward_jumps_per_dest: None,
+			#[cfg(feature = "debug-features")]
+			ignore_revert: false,
 		}
 	}
 
@@ -252,13 +1064,22 @@ impl Config {
 			gas_ext_code_hash: 700,
 			gas_balance: 700,
 			gas_sload: 800,
+			gas_sload_cold: 0,
+			gas_storage_read_warm: 0,
+			gas_account_access_cold: 0,
+			gas_access_list_address: 0,
+			gas_access_list_storage_key: 0,
+			increase_state_access_gas: false,
 			gas_sstore_set: 20000,
 			gas_sstore_reset: 5000,
 			refund_sstore_clears: 15000,
+			max_refund_quotient: 2,
+			has_selfdestruct_refund: true,
 			gas_suicide: 5000,
 			gas_suicide_new_account: 25000,
 			gas_call: 700,
 			gas_expbyte: 50,
+			gas_code_deposit: 200,
 			gas_transaction_create: 53000,
 			gas_transaction_call: 21000,
 			gas_transaction_zero_data: 4,
@@ -268,11 +1089,16 @@ impl Config {
 			err_on_call_with_more_gas: false,
 			empty_considered_exists: false,
 			create_increase_nonce: true,
+			has_create_collision_check: true,
 			call_l64_after_gas: true,
 			stack_limit: 1024,
 			memory_limit: usize::max_value(),
 			call_stack_limit: 1024,
+			create_depth_limit: None,
 			create_contract_limit: Some(0x6000),
+			max_code_size: None,
+			max_logs: None,
+			max_return_data: None,
 			call_stipend: 2300,
 			has_delegate_call: true,
 			has_create2: true,
@@ -282,12 +1108,67 @@ impl Config {
 			has_chain_id: true,
 			has_self_balance: true,
 			has_ext_code_hash: true,
+			precompile_has_empty_code_hash: true,
+			has_eip7702: false,
+			has_eip6780: false,
+			has_base_fee: false,
+			has_blob_base_fee: false,
+			opcode_aliases: &[],
 			estimate: false,
+			ignore_gas: false,
+			per_opcode_gas_cap: None,
+			max_backward_jumps_per_dest: None,
+			#[cfg(feature = "debug-features")]
+			ignore_revert: false,
+		}
+	}
+
+	/// Berlin hard fork configuration: the access costs of EIP-2929 replace
+	/// the flat costs of the state accessing opcodes, which are zero.
+	#[must_use]
+	pub const fn berlin() -> Self {
+		Self {
+			gas_ext_code: 0,
+			gas_ext_code_hash: 0,
+			gas_balance: 0,
+			gas_sload: 0,
+			gas_sload_cold: 2100,
+			gas_storage_read_warm: 100,
+			gas_account_access_cold: 2600,
+			gas_access_list_address: 2400,
+			gas_access_list_storage_key: 1900,
+			increase_state_access_gas: true,
+			gas_sstore_reset: 2900,
+			gas_call: 0,
+			..Self::istanbul()
+		}
+	}
+
+	/// London hard fork configuration.
+	#[must_use]
+	pub const fn london() -> Self {
+		Self {
+			refund_sstore_clears: 4800,
+			max_refund_quotient: 5,
+			has_selfdestruct_refund: false,
+			has_base_fee: true,
+			..Self::berlin()
 		}
 	}
 
+	/// OP Stack L2 configuration, identical to `london()` field for field:
+	/// the Berlin access costs, the EIP-3529 refunds and the base fee. The
+	/// OP Stack makes no VM-level change to the fork it is based on; the L1
+	/// data fee, deposit transactions and the system contracts are all up
+	/// to the host. L2 specific EVM changes belong here.
+	#[must_use]
+	pub const fn optimism() -> Self {
+		Self::london()
+	}
+
 	/// Reference to default configuration
 	pub fn default() -> &'static Config {
 		&CONFIG
 	}
 }
+
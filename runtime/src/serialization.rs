@@ -0,0 +1,72 @@
+//! Versioned borsh layout of `Runtime`.
+//!
+//! Runtimes serialized before the layout was versioned hold the machine,
+//! the status, the return data and the context, in that order. The
+//! versioned layout starts with `VERSIONED`, which cannot be the length of
+//! the call data the legacy layout starts with, then a version byte, the
+//! same fields, and the state added by each version:
+//!
+//! 1. The output region of a pending `CALL`, the gas charged so far for
+//!    the current opcode, and `Machine::resume_state`.
+//!
+//! A runtime deserialized from the legacy layout has none of that state.
+//! A `CALL` it was suspended in still has its output region on the stack,
+//! where `save_return_value` finds it. Neither layout holds the
+//! configuration, see `Runtime::set_config`.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use borsh::{BorshSerialize, BorshDeserialize};
+use borsh::maybestd::io::{Error, ErrorKind, Result, Write};
+use crate::{Context, ExitReason, Machine, Runtime};
+
+/// Marker of the versioned layout, in place of the length prefix of the
+/// call data the legacy layout starts with.
+const VERSIONED: u32 = u32::MAX;
+/// Version written by `serialize`.
+const VERSION: u8 = 1;
+
+impl BorshSerialize for Runtime {
+	fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+		VERSIONED.serialize(writer)?;
+		VERSION.serialize(writer)?;
+		self.machine.serialize(writer)?;
+		self.status.serialize(writer)?;
+		self.return_data_buffer.serialize(writer)?;
+		self.context.serialize(writer)?;
+		// Version 1.
+		self.call_output.serialize(writer)?;
+		self.opcode_gas.serialize(writer)?;
+		let (pre_validate_failure, backward_jumps) = self.machine.resume_state();
+		pre_validate_failure.serialize(writer)?;
+		backward_jumps.serialize(writer)
+	}
+}
+
+impl BorshDeserialize for Runtime {
+	fn deserialize(buf: &mut &[u8]) -> Result<Self> {
+		let version = if buf.starts_with(&VERSIONED.to_le_bytes()) {
+			*buf = &buf[4..];
+			u8::deserialize(buf)?
+		} else {
+			0
+		};
+		if version > VERSION {
+			return Err(Error::new(ErrorKind::InvalidData, "unknown runtime layout version"));
+		}
+
+		let machine = Machine::deserialize(buf)?;
+		let status = core::result::Result::<(), ExitReason>::deserialize(buf)?;
+		let return_data_buffer = Vec::<u8>::deserialize(buf)?;
+		let context = Context::deserialize(buf)?;
+		let mut runtime = Self::from_parts(machine, status, return_data_buffer, context);
+		if version >= 1 {
+			runtime.call_output = Option::<(usize, usize)>::deserialize(buf)?;
+			runtime.opcode_gas = u64::deserialize(buf)?;
+			let pre_validate_failure = Option::<usize>::deserialize(buf)?;
+			let backward_jumps = BTreeMap::<usize, u64>::deserialize(buf)?;
+			runtime.machine.set_resume_state(pre_validate_failure, backward_jumps);
+		}
+		Ok(runtime)
+	}
+}
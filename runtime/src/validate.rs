@@ -0,0 +1,55 @@
+use crate::Config;
+
+/// Feature flag combination of a `Config` that no Ethereum fork has had.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+	/// `flag` is enabled while `requires`, introduced by the same or an
+	/// earlier fork, is not.
+	Requires {
+		flag: &'static str,
+		requires: &'static str,
+	},
+}
+
+impl Config {
+	/// Check that the feature flags are consistent with the Ethereum fork
+	/// history: every flag enabled implies the flags of the same fork and of
+	/// all earlier forks. Gas costs and limits are not checked, and neither
+	/// are combinations some fork may legitimately have.
+	pub fn validate(&self) -> Result<(), ConfigError> {
+		let rules = [
+			// Byzantium.
+			("has_revert", self.has_revert, "has_delegate_call", self.has_delegate_call),
+			("has_revert", self.has_revert, "has_return_data", self.has_return_data),
+			("has_return_data", self.has_return_data, "has_revert", self.has_revert),
+			// Constantinople.
+			("has_bitwise_shifting", self.has_bitwise_shifting, "has_revert", self.has_revert),
+			("has_create2", self.has_create2, "has_bitwise_shifting", self.has_bitwise_shifting),
+			("has_ext_code_hash", self.has_ext_code_hash, "has_create2", self.has_create2),
+			("has_bitwise_shifting", self.has_bitwise_shifting, "has_ext_code_hash", self.has_ext_code_hash),
+			("sstore_gas_metering", self.sstore_gas_metering, "has_create2", self.has_create2),
+			// Istanbul.
+			("has_chain_id", self.has_chain_id, "has_create2", self.has_create2),
+			("has_chain_id", self.has_chain_id, "has_self_balance", self.has_self_balance),
+			("has_self_balance", self.has_self_balance, "has_chain_id", self.has_chain_id),
+			("sstore_revert_under_stipend", self.sstore_revert_under_stipend, "sstore_gas_metering", self.sstore_gas_metering),
+			// Berlin.
+			("increase_state_access_gas", self.increase_state_access_gas, "has_chain_id", self.has_chain_id),
+			// London.
+			("has_base_fee", self.has_base_fee, "has_chain_id", self.has_chain_id),
+			// Cancun.
+			("has_blob_base_fee", self.has_blob_base_fee, "has_base_fee", self.has_base_fee),
+			("has_blob_base_fee", self.has_blob_base_fee, "has_eip6780", self.has_eip6780),
+			("has_eip6780", self.has_eip6780, "has_blob_base_fee", self.has_blob_base_fee),
+			// Prague.
+			("has_eip7702", self.has_eip7702, "has_blob_base_fee", self.has_blob_base_fee),
+		];
+
+		for (flag, enabled, requires, required) in rules {
+			if enabled && !required {
+				return Err(ConfigError::Requires { flag, requires });
+			}
+		}
+		Ok(())
+	}
+}
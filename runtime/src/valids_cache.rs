@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+use std::vec::Vec;
+use crate::{Valids, H256};
+
+/// Jump destination bitmaps memoized by code hash.
+///
+/// A cache created with `new` grows by one bitmap per distinct code, an
+/// eighth of the code size; use `with_limit` to bound it.
+#[derive(Clone, Debug, Default)]
+pub struct ValidsCache {
+	entries: BTreeMap<H256, Vec<u8>>,
+	limit: Option<usize>,
+}
+
+impl ValidsCache {
+	/// Create an empty, unbounded cache.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self { entries: BTreeMap::new(), limit: None }
+	}
+
+	/// Create an empty cache holding at most `limit` bitmaps, and at least
+	/// one. A miss on a full cache evicts an arbitrary entry.
+	#[must_use]
+	pub const fn with_limit(limit: usize) -> Self {
+		let limit = if limit == 0 { 1 } else { limit };
+		Self { entries: BTreeMap::new(), limit: Some(limit) }
+	}
+
+	/// Get the bitmap of `code`, computing and storing it on a miss.
+	/// `code_hash` must be the Keccak-256 hash of `code`: it is the cache
+	/// key and is not checked.
+	pub fn get_or_compute(&mut self, code_hash: H256, code: &[u8]) -> &[u8] {
+		if !self.entries.contains_key(&code_hash) {
+			if let Some(limit) = self.limit {
+				while self.entries.len() >= limit {
+					self.entries.pop_first();
+				}
+			}
+		}
+		self.entries.entry(code_hash).or_insert_with(|| Valids::compute(code))
+	}
+
+	/// Number of cached bitmaps.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	/// Whether the cache is empty.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// Drop every cached bitmap.
+	pub fn clear(&mut self) {
+		self.entries.clear();
+	}
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::{address, call, context, MockHandler};
+use evm_runtime::{AccessListRecorder, Capture, ExitReason, ExitSucceed, Runtime, Valids, H256, U256};
+
+fn run(code: Vec<u8>, handler: &mut AccessListRecorder<MockHandler>) -> ExitReason {
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(address(0xa)));
+	let reason = match runtime.run(u64::MAX, handler).1 {
+		Capture::Exit(reason) => reason,
+		Capture::Trap(_) => panic!("unexpected interrupt"),
+	};
+	reason
+}
+
+#[test]
+fn records_accessed_accounts_and_slots() {
+	let mut handler = AccessListRecorder::new(MockHandler::new());
+	// SLOAD(1), SSTORE(3, 1), SLOAD(1) again, BALANCE(0xc), then CALL 0xb.
+	let code = [
+		vec![0x60, 0x01, 0x54, 0x50],
+		vec![0x60, 0x01, 0x60, 0x03, 0x55],
+		vec![0x60, 0x01, 0x54, 0x50],
+		vec![0x60, 0x0c, 0x31, 0x50],
+		call(address(0xb)),
+	].concat();
+	assert_eq!(run(code, &mut handler), ExitSucceed::Stopped.into());
+
+	assert_eq!(handler.access_list(), vec![
+		(address(0xa), vec![H256::from(U256::from(1)), H256::from(U256::from(3))]),
+		(address(0xb), Vec::new()),
+		(address(0xc), Vec::new()),
+	]);
+}
+
+#[test]
+fn records_accesses_of_reverted_frames() {
+	let mut handler = AccessListRecorder::new(MockHandler::new());
+	// SLOAD(7), then REVERT(0, 0).
+	let code = vec![0x60, 0x07, 0x54, 0x50, 0x60, 0x00, 0x60, 0x00, 0xfd];
+	assert!(matches!(run(code, &mut handler), ExitReason::Revert(_)));
+	assert_eq!(handler.access_list(), vec![(address(0xa), vec![H256::from(U256::from(7))])]);
+}
+
+#[test]
+fn access_list_is_sorted_whatever_the_access_order() {
+	// BALANCE(0xd), SLOAD(9), BALANCE(0xc), SLOAD(2)
+	let code = vec![0x60, 0x0d, 0x31, 0x50, 0x60, 0x09, 0x54, 0x50, 0x60, 0x0c, 0x31, 0x50, 0x60, 0x02, 0x54, 0x50];
+	let lists: Vec<_> = (0..2).map(|_| {
+		let mut handler = AccessListRecorder::new(MockHandler::new());
+		assert_eq!(run(code.clone(), &mut handler), ExitSucceed::Stopped.into());
+		handler.access_list()
+	}).collect();
+
+	assert_eq!(lists[0], lists[1]);
+	assert_eq!(lists[0], vec![
+		(address(0xa), vec![H256::from(U256::from(2)), H256::from(U256::from(9))]),
+		(address(0xc), Vec::new()),
+		(address(0xd), Vec::new()),
+	]);
+}
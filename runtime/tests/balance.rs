@@ -0,0 +1,111 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{ExitSucceed, Handler, H160, U256};
+
+/// Run `code` from a contract holding a balance of 5, returning the value
+/// stored at slot 0.
+fn run(code: Vec<u8>) -> U256 {
+	let contract = address(0xa);
+	let mut handler = MockHandler::new();
+	handler.balances.insert(contract, U256::from(5));
+	handler.deploy(address(0xb), vec![0x00]);
+
+	let (reason, _) = handler.execute(code, Vec::new(), context(contract));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	handler.storage[&(contract, U256::zero())]
+}
+
+/// SSTORE(0, CALL(GAS, to, value, 0, 0, 0, 0))
+fn call_with_value(to: H160, value: u8) -> Vec<u8> {
+	let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, value, 0x73];
+	code.extend_from_slice(to.as_bytes());
+	code.extend_from_slice(&[0x5a, 0xf1, 0x60, 0x00, 0x55]);
+	code
+}
+
+/// SSTORE(0, CREATE(value, 0, 0))
+fn create_with_value(value: u8) -> Vec<u8> {
+	vec![0x60, 0x00, 0x60, 0x00, 0x60, value, 0xf0, 0x60, 0x00, 0x55]
+}
+
+#[test]
+fn call_within_balance_succeeds() {
+	assert_eq!(run(call_with_value(address(0xb), 5)), U256::one());
+}
+
+#[test]
+fn call_over_balance_fails_the_call_only() {
+	assert_eq!(run(call_with_value(address(0xb), 6)), U256::zero());
+}
+
+#[test]
+fn create_within_balance_succeeds() {
+	assert_ne!(run(create_with_value(5)), U256::zero());
+}
+
+#[test]
+fn create_over_balance_fails_the_create_only() {
+	assert_eq!(run(create_with_value(6)), U256::zero());
+}
+
+#[test]
+fn call_with_value_to_account_without_code_transfers_value() {
+	let contract = address(0xa);
+	let recipient = address(0xc);
+	let mut handler = MockHandler::new();
+	handler.balances.insert(contract, U256::from(5));
+
+	let (reason, _) = handler.execute(call_with_value(recipient, 3), Vec::new(), context(contract));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	assert_eq!(handler.storage[&(contract, U256::zero())], U256::one());
+	assert_eq!(handler.balance(contract), U256::from(2));
+	assert_eq!(handler.balance(recipient), U256::from(3));
+	// No frame was executed for the recipient.
+	assert_eq!(handler.code_queries.get(), 0);
+}
+
+#[test]
+fn reverted_call_with_value_restores_balances() {
+	let contract = address(0xa);
+	let mut handler = MockHandler::new();
+	handler.balances.insert(contract, U256::from(5));
+	handler.deploy(address(0xb), common::revert());
+
+	let (reason, _) = handler.execute(call_with_value(address(0xb), 3), Vec::new(), context(contract));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	assert_eq!(handler.storage[&(contract, U256::zero())], U256::zero());
+	assert_eq!(handler.balance(contract), U256::from(5));
+	assert_eq!(handler.balance(address(0xb)), U256::zero());
+}
+
+#[test]
+fn callee_sees_the_transferred_value() {
+	let contract = address(0xa);
+	let mut handler = MockHandler::new();
+	handler.balances.insert(contract, U256::from(5));
+	// SSTORE(0, SELFBALANCE)
+	handler.deploy(address(0xb), vec![0x47, 0x60, 0x00, 0x55]);
+
+	let (reason, _) = handler.execute(call_with_value(address(0xb), 3), Vec::new(), context(contract));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	assert_eq!(handler.storage[&(address(0xb), U256::zero())], U256::from(3));
+}
+
+#[test]
+fn balance_ignores_the_high_bytes_of_the_address_word() {
+	let target = address(0xc);
+	let mut handler = MockHandler::new();
+	handler.balances.insert(target, U256::from(7));
+
+	// SSTORE(0, BALANCE(0xff..ff ++ target))
+	let mut code = vec![0x7f];
+	code.extend_from_slice(&[0xff; 12]);
+	code.extend_from_slice(target.as_bytes());
+	code.extend_from_slice(&[0x31, 0x60, 0x00, 0x55, 0x00]);
+
+	let contract = address(0xa);
+	let (reason, _) = handler.execute(code, Vec::new(), context(contract));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	assert_eq!(handler.storage[&(contract, U256::zero())], U256::from(7));
+}
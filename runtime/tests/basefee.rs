@@ -0,0 +1,30 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{Config, ExitError, ExitReason, ExitSucceed, U256};
+
+static ISTANBUL: Config = Config::istanbul();
+static LONDON: Config = Config::london();
+
+/// Run SSTORE(0, BASEFEE), returning the exit reason and slot 0.
+fn basefee(config: &'static Config, base_fee: U256) -> (ExitReason, U256) {
+	let mut handler = MockHandler { config, base_fee, ..MockHandler::new() };
+	let (reason, _) = handler.execute(vec![0x48, 0x60, 0x00, 0x55], Vec::new(), context(address(0xa)));
+	(reason, handler.storage.get(&(address(0xa), U256::zero())).copied().unwrap_or_default())
+}
+
+#[test]
+fn basefee_before_london_is_an_unassigned_opcode() {
+	let (reason, _) = basefee(&ISTANBUL, U256::from(100));
+	assert_eq!(reason, ExitError::OutOfGas.into());
+}
+
+#[test]
+fn basefee_pushes_the_block_base_fee() {
+	assert_eq!(basefee(&LONDON, U256::from(100)), (ExitSucceed::Stopped.into(), U256::from(100)));
+}
+
+#[test]
+fn basefee_without_a_base_fee_pushes_zero() {
+	assert_eq!(basefee(&LONDON, U256::zero()), (ExitSucceed::Stopped.into(), U256::zero()));
+}
@@ -0,0 +1,16 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{ExitReason, ExitSucceed, U256};
+
+#[test]
+fn block_fields_are_read_once_per_frame() {
+	let mut handler = MockHandler { block_number: U256::from(7), ..MockHandler::new() };
+	// SSTORE(0, NUMBER), SSTORE(1, NUMBER), POP(TIMESTAMP), POP(CHAINID)
+	let code = vec![0x43, 0x60, 0x00, 0x55, 0x43, 0x60, 0x01, 0x55, 0x42, 0x50, 0x46, 0x50];
+	let (reason, _) = handler.execute(code, Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+	assert_eq!(handler.storage.get(&(address(0xa), U256::zero())), Some(&U256::from(7)));
+	assert_eq!(handler.storage.get(&(address(0xa), U256::one())), Some(&U256::from(7)));
+	assert_eq!(handler.block_queries.get(), 1);
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::{address, context, keccak, MockHandler};
+use evm_runtime::{ExitSucceed, H256, U256};
+
+/// BLOCKHASH of `number` in block `current`.
+fn blockhash_in(current: u64, number: u64) -> H256 {
+	blockhash_u256(U256::from(current), U256::from(number))
+}
+
+/// BLOCKHASH of `number` in block `current`, both as full words.
+fn blockhash_u256(current: U256, number: U256) -> H256 {
+	let mut handler = MockHandler { block_number: current, ..MockHandler::new() };
+	// SSTORE(0, BLOCKHASH(number))
+	let mut code = vec![0x7f];
+	code.extend_from_slice(&<[u8; 32]>::from(number));
+	code.extend_from_slice(&[0x40, 0x60, 0x00, 0x55]);
+	let (reason, _) = handler.execute(code, Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	H256::from(handler.storage[&(address(0xa), U256::zero())])
+}
+
+/// BLOCKHASH of `number` in block 1000.
+fn blockhash(number: u64) -> H256 {
+	blockhash_in(1000, number)
+}
+
+fn hash_of(number: u64) -> H256 {
+	keccak(&<[u8; 32]>::from(U256::from(number)))
+}
+
+#[test]
+fn recent_block_hashes_come_from_the_handler() {
+	assert_eq!(blockhash(999), hash_of(999));
+	assert_eq!(blockhash(900), hash_of(900));
+}
+
+#[test]
+fn oldest_available_block_is_256_blocks_back() {
+	assert_eq!(blockhash(744), hash_of(744));
+	assert_eq!(blockhash(743), H256::zero());
+}
+
+#[test]
+fn current_and_future_block_hashes_are_zero() {
+	assert_eq!(blockhash(1000), H256::zero());
+	assert_eq!(blockhash(1001), H256::zero());
+	assert_eq!(blockhash(u64::MAX), H256::zero());
+}
+
+#[test]
+fn block_zero_is_only_available_in_the_next_256_blocks() {
+	assert_eq!(blockhash_in(0, 0), H256::zero());
+	assert_eq!(blockhash_in(1, 0), hash_of(0));
+	assert_eq!(blockhash_in(256, 0), hash_of(0));
+	assert_eq!(blockhash_in(257, 0), H256::zero());
+}
+
+#[test]
+fn extreme_block_numbers_do_not_overflow() {
+	assert_eq!(blockhash_in(5, u64::MAX), H256::zero());
+	assert_eq!(blockhash_in(u64::MAX, u64::MAX), H256::zero());
+	assert_eq!(blockhash_in(u64::MAX, u64::MAX - 1), hash_of(u64::MAX - 1));
+	assert_eq!(blockhash_in(u64::MAX, u64::MAX - 256), hash_of(u64::MAX - 256));
+	assert_eq!(blockhash_in(u64::MAX, u64::MAX - 257), H256::zero());
+	assert_eq!(blockhash_u256(U256::from(5), U256::MAX), H256::zero());
+	assert_eq!(blockhash_u256(U256::from(5), U256::from(u64::MAX) + 1), H256::zero());
+}
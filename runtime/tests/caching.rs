@@ -0,0 +1,49 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{CachingHandler, Capture, ExitReason, ExitSucceed, Handler, Runtime, Valids, U256};
+
+fn run(code: Vec<u8>, handler: &mut CachingHandler<MockHandler>) -> ExitReason {
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(address(0xa)));
+	let reason = match runtime.run(u64::MAX, handler).1 {
+		Capture::Exit(reason) => reason,
+		Capture::Trap(_) => panic!("unexpected interrupt"),
+	};
+	reason
+}
+
+/// Code storing `value` at `slot`.
+fn sstore(slot: u8, value: u8) -> Vec<u8> {
+	vec![0x60, value, 0x60, slot, 0x55]
+}
+
+#[test]
+fn repeated_writes_flush_once() {
+	let mut handler = CachingHandler::new(MockHandler::new());
+	let code = (1..=5).flat_map(|value| sstore(0, value)).collect();
+	assert_eq!(run(code, &mut handler), ExitSucceed::Stopped.into());
+	assert_eq!(handler.storage(address(0xa), U256::zero()), U256::from(5));
+	assert_eq!(handler.inner().storage_writes, 0);
+
+	let inner = handler.into_inner().unwrap();
+	assert_eq!(inner.storage_writes, 1);
+	assert_eq!(inner.storage[&(address(0xa), U256::zero())], U256::from(5));
+}
+
+#[test]
+fn revert_discards_buffered_writes() {
+	let mut handler = CachingHandler::new(MockHandler::new());
+	handler.set_storage(address(0xa), U256::zero(), U256::one()).unwrap();
+
+	handler.enter_frame();
+	let code = [sstore(0, 2), sstore(1, 3), vec![0x60, 0x00, 0x60, 0x00, 0xfd]].concat();
+	assert!(matches!(run(code, &mut handler), ExitReason::Revert(_)));
+	handler.exit_frame(true);
+
+	assert_eq!(handler.storage(address(0xa), U256::zero()), U256::one());
+	assert_eq!(handler.storage(address(0xa), U256::one()), U256::zero());
+	let inner = handler.into_inner().unwrap();
+	assert_eq!(inner.storage_writes, 1);
+	assert_eq!(inner.storage.get(&(address(0xa), U256::one())), None);
+}
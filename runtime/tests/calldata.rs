@@ -0,0 +1,103 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{ExitSucceed, U256};
+
+/// Run `code` with `data` as input, returning the value stored at slot 0.
+fn run(code: Vec<u8>, data: Vec<u8>) -> U256 {
+	let mut handler = MockHandler::new();
+	let (reason, _) = handler.execute(code, data, context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	handler.storage.get(&(address(0xa), U256::zero())).copied().unwrap_or_default()
+}
+
+fn input() -> Vec<u8> {
+	(1..=40).collect()
+}
+
+/// The 32-byte word made of `bytes` followed by zeros.
+fn word(bytes: &[u8]) -> U256 {
+	let mut word = [0_u8; 32];
+	word[..bytes.len()].copy_from_slice(bytes);
+	U256::from_big_endian(&word)
+}
+
+/// Code pushing `offset` as a 32-byte immediate.
+fn push32(offset: U256) -> Vec<u8> {
+	let mut code = vec![0x7f];
+	code.extend_from_slice(&<[u8; 32]>::from(offset));
+	code
+}
+
+/// SSTORE(0, CALLDATALOAD(offset))
+fn calldataload(offset: U256) -> Vec<u8> {
+	[push32(offset), vec![0x35, 0x60, 0x00, 0x55]].concat()
+}
+
+/// Fill the first memory word with ones, CALLDATACOPY 32 bytes from `offset`
+/// over it, then SSTORE(0, MLOAD(0)).
+fn calldatacopy(offset: U256) -> Vec<u8> {
+	[
+		push32(U256::MAX),
+		vec![0x60, 0x00, 0x52, 0x60, 0x20],
+		push32(offset),
+		vec![0x60, 0x00, 0x37, 0x60, 0x00, 0x51, 0x60, 0x00, 0x55],
+	].concat()
+}
+
+#[test]
+fn calldataload_straddling_the_end_zero_pads() {
+	assert_eq!(run(calldataload(U256::from(24)), input()), word(&input()[24..]));
+	assert_eq!(run(calldataload(U256::from(39)), input()), word(&[40]));
+}
+
+#[test]
+fn calldataload_beyond_the_end_is_zero() {
+	assert_eq!(run(calldataload(U256::from(40)), input()), U256::zero());
+	assert_eq!(run(calldataload(U256::from(1000)), input()), U256::zero());
+	assert_eq!(run(calldataload(U256::MAX), input()), U256::zero());
+}
+
+#[test]
+fn calldatacopy_straddling_the_end_zero_fills() {
+	assert_eq!(run(calldatacopy(U256::from(24)), input()), word(&input()[24..]));
+}
+
+#[test]
+fn calldatacopy_beyond_the_end_zero_fills() {
+	assert_eq!(run(calldatacopy(U256::from(40)), input()), U256::zero());
+	assert_eq!(run(calldatacopy(U256::MAX), input()), U256::zero());
+}
+
+#[test]
+fn calldatasize_of_empty_calldata_is_zero() {
+	// SSTORE(0, CALLDATASIZE + 1)
+	assert_eq!(run(vec![0x36, 0x60, 0x01, 0x01, 0x60, 0x00, 0x55], Vec::new()), U256::one());
+}
+
+#[test]
+fn calldataload_of_empty_calldata_is_zero() {
+	for offset in [U256::zero(), U256::from(31), U256::MAX] {
+		assert_eq!(run(calldataload(offset), Vec::new()), U256::zero());
+	}
+}
+
+#[test]
+fn calldatacopy_of_empty_calldata_zero_fills() {
+	for offset in [U256::zero(), U256::from(31), U256::MAX] {
+		assert_eq!(run(calldatacopy(offset), Vec::new()), U256::zero());
+	}
+}
+
+#[test]
+fn empty_calldatacopy_of_empty_calldata_leaves_memory() {
+	// Fill the first memory word with ones, CALLDATACOPY(0, MAX, 0), then
+	// SSTORE(0, MLOAD(0)).
+	let code = [
+		push32(U256::MAX),
+		vec![0x60, 0x00, 0x52, 0x60, 0x00],
+		push32(U256::MAX),
+		vec![0x60, 0x00, 0x37, 0x60, 0x00, 0x51, 0x60, 0x00, 0x55],
+	].concat();
+	assert_eq!(run(code, Vec::new()), U256::MAX);
+}
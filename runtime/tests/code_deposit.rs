@@ -0,0 +1,61 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{Config, ExitSucceed, CONFIG, U256};
+
+static FREE_DEPOSIT: Config = Config { gas_code_deposit: 0, ..Config::istanbul() };
+
+/// Code creating a contract whose init code returns 100 zero bytes, then
+/// storing the created address at slot 0.
+fn create_100_bytes() -> Vec<u8> {
+	vec![
+		// MSTORE(0, PUSH1 100 PUSH1 0 RETURN)
+		0x64, 0x60, 0x64, 0x60, 0x00, 0xf3, 0x60, 0x00, 0x52,
+		// CREATE(0, 27, 5)
+		0x60, 0x05, 0x60, 0x1b, 0x60, 0x00, 0xf0,
+		0x60, 0x00, 0x55,
+	]
+}
+
+/// Gas used and created address, with `gas` available, resolving the
+/// create through an interrupt if `trap` is set.
+fn run_with(config: &'static Config, gas: u64, trap: bool) -> (u64, U256) {
+	let mut handler = MockHandler { config, gas_left: gas, trap, ..MockHandler::new() };
+	let (reason, _) = handler.execute(create_100_bytes(), Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	let created = handler.storage.get(&(address(0xa), U256::zero())).copied().unwrap_or_default();
+	(gas - handler.gas_left, created)
+}
+
+fn run(config: &'static Config, gas: u64) -> (u64, U256) {
+	run_with(config, gas, false)
+}
+
+#[test]
+fn charges_deposit_per_deployed_byte() {
+	let (with_deposit, created) = run(&CONFIG, 1_000_000);
+	let (without_deposit, _) = run(&FREE_DEPOSIT, 1_000_000);
+	assert!(!created.is_zero());
+	assert_eq!(with_deposit - without_deposit, 20000);
+}
+
+#[test]
+fn create_fails_without_gas_for_deposit() {
+	let (_, created) = run(&CONFIG, 10_000);
+	assert!(created.is_zero());
+}
+
+#[test]
+fn charges_deposit_of_interrupted_creates() {
+	let (with_deposit, created) = run_with(&CONFIG, 1_000_000, true);
+	let (without_deposit, _) = run_with(&FREE_DEPOSIT, 1_000_000, true);
+	assert!(!created.is_zero());
+	assert_eq!(with_deposit - without_deposit, 20000);
+	assert_eq!(run_with(&CONFIG, 1_000_000, false).0, with_deposit);
+}
+
+#[test]
+fn interrupted_create_fails_without_gas_for_deposit() {
+	let (_, created) = run_with(&CONFIG, 10_000, true);
+	assert!(created.is_zero());
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "code-hash-cache")]
+
+mod common;
+
+use common::{address, call, context, MockHandler};
+use evm_runtime::{ExitSucceed, H160};
+
+/// Code pushing and dropping EXTCODEHASH of `target`.
+fn extcodehash(target: H160) -> Vec<u8> {
+	let mut code = vec![0x73];
+	code.extend_from_slice(target.as_bytes());
+	code.extend_from_slice(&[0x3f, 0x50]);
+	code
+}
+
+#[test]
+fn repeated_extcodehash_hits_the_cache() {
+	let target = address(0xb);
+	let mut handler = MockHandler::new();
+	handler.deploy(target, vec![0x00]);
+
+	let code = [extcodehash(target), extcodehash(target), extcodehash(target)].concat();
+	let (reason, _) = handler.execute(code, Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	assert_eq!(handler.code_hash_queries.get(), 1);
+}
+
+#[test]
+fn calls_invalidate_the_cache() {
+	let target = address(0xb);
+	let mut handler = MockHandler::new();
+	handler.deploy(target, vec![0x00]);
+
+	let code = [extcodehash(target), call(target), vec![0x50], extcodehash(target)].concat();
+	let (reason, _) = handler.execute(code, Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	assert_eq!(handler.code_hash_queries.get(), 2);
+}
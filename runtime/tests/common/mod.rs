@@ -0,0 +1,488 @@
+//! Synchronous in-memory handler shared by the runtime integration tests.
+
+#![allow(dead_code)]
+
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet};
+use evm_runtime::{
+	effective_gas_price, Capture, Config, Context, CreateScheme, ExitError, ExitFatal, ExitReason, ExitSucceed, Handler, Machine, Opcode, Runtime,
+	Stack, Transfer, Valids, CONFIG, H160, H256, U256,
+};
+use sha3::{Digest, Keccak256};
+
+/// Log emitted by a contract.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Log {
+	pub address: H160,
+	pub topics: Vec<H256>,
+	pub data: Vec<u8>,
+}
+
+/// State of the handler at the time a frame was entered.
+pub struct Checkpoint {
+	logs: usize,
+	storage: BTreeMap<(H160, U256), U256>,
+	balances: BTreeMap<H160, U256>,
+	refund: i64,
+	selfdestructed: BTreeSet<H160>,
+}
+
+/// Addresses and slots passed to `prefetch`.
+pub type Prefetch = (Vec<H160>, Vec<(H160, H256)>);
+
+/// Handler executing nested frames synchronously, journaling logs, storage,
+/// balances, refunds and self-destructs on `enter_frame`/`exit_frame`.
+pub struct MockHandler {
+	pub code: BTreeMap<H160, Vec<u8>>,
+	pub balances: BTreeMap<H160, U256>,
+	pub nonces: BTreeMap<H160, U256>,
+	pub storage: BTreeMap<(H160, U256), U256>,
+	pub logs: Vec<Log>,
+	pub gas_left: u64,
+	/// Refunds recorded by the runtime.
+	pub refund: i64,
+	pub selfdestructed: BTreeSet<H160>,
+	/// Number of `set_storage` calls.
+	pub storage_writes: usize,
+	/// Charge EIP-2929 account access costs for `CALL` in `pre_validate`.
+	pub access_gas: bool,
+	pub warm_accounts: BTreeSet<H160>,
+	/// Number of `code_hash` calls.
+	pub code_hash_queries: Cell<usize>,
+	/// Number of `code` calls.
+	pub code_queries: Cell<usize>,
+	/// Number of `balance` calls.
+	pub balance_queries: Cell<usize>,
+	/// Number of `block_number` calls.
+	pub block_queries: Cell<usize>,
+	/// Arguments of every `storage_batch` call.
+	pub storage_batches: RefCell<Vec<(H160, Vec<U256>)>>,
+	/// Arguments of every `prefetch` call.
+	pub prefetched: RefCell<Vec<Prefetch>>,
+	/// Precompiles behaving like the identity one, besides `IDENTITY` and
+	/// `ECRECOVER`.
+	pub precompiles: BTreeSet<H160>,
+	/// Gas credited back by `pre_validate` for an opcode, mimicking a handler
+	/// applying a refund twice.
+	pub buggy_refund: Option<(Opcode, u64)>,
+	/// Fail `try_keccak256_h256` with `ExitFatal::Killed`.
+	pub keccak_fails: bool,
+	/// Return interrupts from `call` and `create` instead of executing them.
+	pub trap: bool,
+	/// Number of create frames currently executing.
+	pub create_depth: usize,
+	/// Gas forwarded to every `create`.
+	pub create_gas: Vec<Option<u64>>,
+	/// Leave nonces untouched in `inc_nonce`, like the default implementation.
+	pub skip_inc_nonce: bool,
+	pub gas_price: U256,
+	pub base_fee: U256,
+	pub block_number: U256,
+	pub max_priority_fee: U256,
+	pub max_fee: U256,
+	/// Configuration of every runtime the handler executes.
+	pub config: &'static Config,
+	pub checkpoints: Vec<Checkpoint>,
+}
+
+// `&'static Config` has no default.
+impl Default for MockHandler {
+	fn default() -> Self {
+		Self {
+			code: Default::default(),
+			balances: Default::default(),
+			nonces: Default::default(),
+			storage: Default::default(),
+			logs: Default::default(),
+			gas_left: Default::default(),
+			refund: Default::default(),
+			selfdestructed: Default::default(),
+			storage_writes: Default::default(),
+			access_gas: Default::default(),
+			warm_accounts: Default::default(),
+			code_hash_queries: Default::default(),
+			code_queries: Default::default(),
+			balance_queries: Default::default(),
+			block_queries: Default::default(),
+			storage_batches: Default::default(),
+			prefetched: Default::default(),
+			precompiles: Default::default(),
+			buggy_refund: Default::default(),
+			keccak_fails: Default::default(),
+			trap: Default::default(),
+			create_depth: Default::default(),
+			create_gas: Default::default(),
+			skip_inc_nonce: Default::default(),
+			gas_price: Default::default(),
+			base_fee: Default::default(),
+			block_number: Default::default(),
+			max_priority_fee: Default::default(),
+			max_fee: Default::default(),
+			config: &CONFIG,
+			checkpoints: Default::default(),
+		}
+	}
+}
+
+impl MockHandler {
+	pub fn new() -> Self {
+		Self { gas_left: 1_000_000, ..Self::default() }
+	}
+
+	/// Install `code` at `address`.
+	pub fn deploy(&mut self, address: H160, code: Vec<u8>) {
+		self.code.insert(address, code);
+	}
+
+	/// Execute `code` to completion in the given context, returning the exit
+	/// reason and the return value.
+	pub fn execute(&mut self, code: Vec<u8>, data: Vec<u8>, context: Context) -> (ExitReason, Vec<u8>) {
+		let valids = Valids::compute(&code);
+		let mut runtime = Runtime::new_with_config(code, valids, data, context, self.config);
+		let reason = run_to_exit(&mut runtime, self);
+		(reason, runtime.machine().return_value())
+	}
+
+	fn execute_create(
+		&mut self,
+		caller: H160,
+		address: H160,
+		value: U256,
+		init_code: Vec<u8>,
+	) -> (ExitReason, Option<H160>, Vec<u8>) {
+		let context = Context { address, caller, apparent_value: value };
+		self.create_depth += 1;
+		let (reason, output) = self.execute(init_code, Vec::new(), context);
+		self.create_depth -= 1;
+		if reason.is_succeed() {
+			self.deploy(address, output.clone());
+		}
+		(reason, Some(address), output)
+	}
+
+	fn execute_call(&mut self, code_address: H160, input: Vec<u8>, context: Context) -> (ExitReason, Vec<u8>) {
+		if code_address == IDENTITY || self.precompiles.contains(&code_address) {
+			return (ExitSucceed::Returned.into(), input);
+		}
+
+		if !self.has_code(code_address) {
+			return (ExitSucceed::Stopped.into(), Vec::new());
+		}
+		let code = self.code(code_address);
+		self.execute(code, input, context)
+	}
+}
+
+/// Interrupt returned by `call` and `create` when `MockHandler::trap` is set,
+/// carrying what `call_sync` and `create_sync` need to run it.
+#[derive(Debug)]
+pub enum Trapped {
+	Call { code_address: H160, input: Vec<u8>, context: Context },
+	Create { caller: H160, scheme: CreateScheme, address: H160, value: U256, init_code: Vec<u8> },
+}
+
+/// Run `runtime` until it exits, resolving interrupts synchronously.
+pub fn run_to_exit(runtime: &mut Runtime, handler: &mut MockHandler) -> ExitReason {
+	loop {
+		match runtime.run(u64::MAX, handler).1 {
+			Capture::Exit(reason) => return reason,
+			Capture::Trap(resolve) => {
+				let _ = Runtime::resolve_interrupt_sync(resolve, handler);
+			},
+		}
+	}
+}
+
+/// Address of the identity precompile, the only one the mock implements.
+pub const IDENTITY: H160 = H160([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4]);
+
+/// Address of the modexp precompile. The mock only prices it, rejecting
+/// lengths over 1024 bytes; add it to `precompiles` to call it.
+pub const MODEXP: H160 = H160([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 5]);
+
+/// Address of the ecrecover precompile. The mock reports it as a precompile
+/// but calls to it return empty output.
+pub const ECRECOVER: H160 = H160([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+pub fn keccak(data: &[u8]) -> H256 {
+	H256::from_slice(Keccak256::digest(data).as_slice())
+}
+
+pub fn address(n: u64) -> H160 {
+	H160::from(U256::from(n))
+}
+
+pub fn context(address: H160) -> Context {
+	Context { address, caller: H160::default(), apparent_value: U256::zero() }
+}
+
+/// Code writing `tag` to memory and emitting it as a one-byte `LOG0`.
+pub fn log_byte(tag: u8) -> Vec<u8> {
+	vec![0x60, tag, 0x60, 0x00, 0x53, 0x60, 0x01, 0x60, 0x00, 0xa0]
+}
+
+/// Code performing a value-less `CALL` to `to` with empty input and output,
+/// leaving the success flag on the stack.
+pub fn call(to: H160) -> Vec<u8> {
+	let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+	code.extend_from_slice(to.as_bytes());
+	code.extend_from_slice(&[0x5a, 0xf1]);
+	code
+}
+
+/// Code reverting with empty return data.
+pub fn revert() -> Vec<u8> {
+	vec![0x60, 0x00, 0x60, 0x00, 0xfd]
+}
+
+impl Handler for MockHandler {
+	type CreateInterrupt = Trapped;
+	type CreateFeedback = core::convert::Infallible;
+	type CallInterrupt = Trapped;
+	type CallFeedback = core::convert::Infallible;
+
+	fn keccak256_h256(&self, data: &[u8]) -> H256 { keccak(data) }
+	fn try_keccak256_h256(&self, data: &[u8]) -> Result<H256, ExitFatal> {
+		if self.keccak_fails { Err(ExitFatal::Killed) } else { Ok(keccak(data)) }
+	}
+
+	fn nonce(&self, address: H160) -> U256 { self.nonces.get(&address).copied().unwrap_or_default() }
+	fn balance(&self, address: H160) -> U256 {
+		self.balance_queries.set(self.balance_queries.get() + 1);
+		self.balances.get(&address).copied().unwrap_or_default()
+	}
+	fn code_size(&self, address: H160) -> U256 { U256::from(self.code(address).len()) }
+	fn code_hash(&self, address: H160) -> H256 {
+		self.code_hash_queries.set(self.code_hash_queries.get() + 1);
+		keccak(&self.code(address))
+	}
+	fn code(&self, address: H160) -> Vec<u8> {
+		self.code_queries.set(self.code_queries.get() + 1);
+		self.code.get(&address).cloned().unwrap_or_default()
+	}
+	fn has_code(&self, address: H160) -> bool {
+		matches!(self.code.get(&address), Some(code) if !code.is_empty())
+	}
+	fn valids(&self, address: H160) -> Vec<u8> { Valids::compute(&self.code(address)) }
+	fn prefetch(&self, addresses: &[H160], slots: &[(H160, H256)]) {
+		self.prefetched.borrow_mut().push((addresses.to_vec(), slots.to_vec()));
+	}
+	fn storage(&self, address: H160, index: U256) -> U256 {
+		self.storage.get(&(address, index)).copied().unwrap_or_default()
+	}
+	fn storage_batch(&self, address: H160, indices: &[U256]) -> Vec<U256> {
+		self.storage_batches.borrow_mut().push((address, indices.to_vec()));
+		indices.iter().map(|index| self.storage(address, *index)).collect()
+	}
+
+	fn gas_left(&self) -> U256 { U256::from(self.gas_left) }
+	fn gas_price(&self) -> U256 { self.gas_price }
+	fn effective_gas_price(&self) -> U256 {
+		effective_gas_price(self.base_fee, self.max_priority_fee, self.max_fee)
+	}
+	fn origin(&self) -> H160 { H160::default() }
+	fn block_hash(&self, number: U256) -> H256 { keccak(&<[u8; 32]>::from(number)) }
+	fn block_number(&self) -> U256 {
+		self.block_queries.set(self.block_queries.get() + 1);
+		self.block_number
+	}
+	fn block_coinbase(&self) -> H160 { H160::default() }
+	fn block_timestamp(&self) -> U256 { U256::zero() }
+	fn block_difficulty(&self) -> U256 { U256::zero() }
+	fn block_gas_limit(&self) -> U256 { U256::zero() }
+	fn chain_id(&self) -> U256 { U256::zero() }
+	fn block_base_fee_per_gas(&self) -> U256 { self.base_fee }
+
+	fn set_storage(&mut self, address: H160, index: U256, value: U256) -> Result<(), ExitError> {
+		self.storage.insert((address, index), value);
+		self.storage_writes += 1;
+		Ok(())
+	}
+
+	fn replace_storage(&mut self, address: H160, index: U256, value: U256) -> Result<U256, ExitError> {
+		self.storage_writes += 1;
+		Ok(self.storage.insert((address, index), value).unwrap_or_default())
+	}
+
+	fn log(&mut self, address: H160, topics: Vec<H256>, data: Vec<u8>) -> Result<(), ExitError> {
+		self.logs.push(Log { address, topics, data });
+		Ok(())
+	}
+
+	fn log_count(&self) -> usize {
+		self.logs.len()
+	}
+
+	fn mark_delete(&mut self, _address: H160, _target: H160) -> Result<(), ExitError> {
+		Ok(())
+	}
+
+	fn selfdestruct(&mut self, address: H160, beneficiary: H160, balance: U256) -> Result<(), ExitError> {
+		self.selfdestructed.insert(address);
+		// Every contract counts as created in the current transaction.
+		let remaining = self.balance(address) - balance;
+		self.balances.insert(address, remaining);
+		if beneficiary != address {
+			let credited = self.balance(beneficiary) + balance;
+			self.balances.insert(beneficiary, credited);
+		}
+		Ok(())
+	}
+
+	fn inc_nonce(&mut self, address: H160) -> Result<bool, ExitError> {
+		if self.skip_inc_nonce {
+			return Ok(false);
+		}
+		let nonce = self.nonce(address) + 1;
+		self.nonces.insert(address, nonce);
+		Ok(true)
+	}
+
+	fn create(
+		&mut self,
+		caller: H160,
+		scheme: CreateScheme,
+		address: H160,
+		value: U256,
+		init_code: Vec<u8>,
+		target_gas: Option<u64>,
+	) -> Capture<(ExitReason, Option<H160>, Vec<u8>), Self::CreateInterrupt> {
+		self.create_gas.push(target_gas);
+		if self.trap {
+			return Capture::Trap(Trapped::Create { caller, scheme, address, value, init_code });
+		}
+		Capture::Exit(self.execute_create(caller, address, value, init_code))
+	}
+
+	fn create_sync(
+		&mut self,
+		interrupt: Trapped,
+	) -> Capture<(ExitReason, Option<H160>, Vec<u8>), Self::CreateInterrupt> {
+		match interrupt {
+			Trapped::Create { caller, address, value, init_code, .. } => {
+				Capture::Exit(self.execute_create(caller, address, value, init_code))
+			},
+			Trapped::Call { .. } => panic!("call interrupt resolved as a create"),
+		}
+	}
+
+	fn call(
+		&mut self,
+		code_address: H160,
+		_transfer: Option<Transfer>,
+		input: Vec<u8>,
+		_target_gas: Option<u64>,
+		_is_static: bool,
+		context: Context,
+	) -> Capture<(ExitReason, Vec<u8>), Self::CallInterrupt> {
+		if self.trap {
+			return Capture::Trap(Trapped::Call { code_address, input, context });
+		}
+		Capture::Exit(self.execute_call(code_address, input, context))
+	}
+
+	fn call_sync(&mut self, interrupt: Trapped) -> Capture<(ExitReason, Vec<u8>), Self::CallInterrupt> {
+		match interrupt {
+			Trapped::Call { code_address, input, context } => {
+				Capture::Exit(self.execute_call(code_address, input, context))
+			},
+			Trapped::Create { .. } => panic!("create interrupt resolved as a call"),
+		}
+	}
+
+	fn transfer(&mut self, transfer: &Transfer) -> Result<(), ExitError> {
+		if transfer.value.is_zero() {
+			return Ok(());
+		}
+		// The runtime has already checked the source balance.
+		let debited = self.balance(transfer.source) - transfer.value;
+		self.balances.insert(transfer.source, debited);
+		let credited = self.balance(transfer.target) + transfer.value;
+		self.balances.insert(transfer.target, credited);
+		Ok(())
+	}
+
+	fn is_precompile(&self, address: H160) -> bool {
+		address == IDENTITY || address == ECRECOVER || self.precompiles.contains(&address)
+	}
+
+	fn precompile_gas(&self, address: H160, input: &[u8]) -> Result<u64, ExitError> {
+		// Like modexp, rejects base, exponent and modulus lengths too large
+		// to price.
+		if address == MODEXP {
+			let mut header = input.chunks(32).take(3).map(U256::from_big_endian);
+			if header.any(|len| len > U256::from(1024)) {
+				return Err(ExitError::OutOfGas);
+			}
+		}
+		Ok(15 + 3 * (input.len() as u64).div_ceil(32))
+	}
+
+	fn create_depth(&self) -> usize {
+		self.create_depth
+	}
+
+	fn enter_frame(&mut self) {
+		self.checkpoints.push(Checkpoint {
+			logs: self.logs.len(),
+			storage: self.storage.clone(),
+			balances: self.balances.clone(),
+			refund: self.refund,
+			selfdestructed: self.selfdestructed.clone(),
+		});
+	}
+
+	fn exit_frame(&mut self, reverted: bool) {
+		let checkpoint = self.checkpoints.pop().expect("exit_frame without enter_frame");
+		if reverted {
+			self.logs.truncate(checkpoint.logs);
+			self.storage = checkpoint.storage;
+			self.balances = checkpoint.balances;
+			self.refund = checkpoint.refund;
+			self.selfdestructed = checkpoint.selfdestructed;
+		}
+	}
+
+	fn record_cost(&mut self, cost: u64) -> Result<(), ExitError> {
+		self.gas_left = self.gas_left.checked_sub(cost).ok_or(ExitError::OutOfGas)?;
+		Ok(())
+	}
+
+	fn pre_validate(&mut self, _context: &Context, opcode: Opcode, stack: &Stack) -> Result<(), ExitError> {
+		// Every opcode costs one gas.
+		self.gas_left = self.gas_left.checked_sub(1).ok_or(ExitError::OutOfGas)?;
+		if let Some((refunded, refund)) = self.buggy_refund {
+			if opcode == refunded {
+				self.gas_left += refund;
+			}
+		}
+		if self.access_gas && opcode == Opcode::CALL {
+			let target = H160::from(H256::from(stack.peek(1)?));
+			let cost = if self.is_cold_account(target) { 2600 } else { 100 };
+			self.warm_account(target);
+			self.record_cost(cost)?;
+		}
+		Ok(())
+	}
+
+	fn preview_gas_cost(&self, _context: &Context, _opcode: Opcode, _machine: &Machine) -> Option<u64> {
+		Some(1)
+	}
+
+	fn is_selfdestructed(&self, address: H160) -> bool {
+		self.selfdestructed.contains(&address)
+	}
+
+	fn record_refund(&mut self, refund: i64) -> Result<(), ExitError> {
+		self.refund += refund;
+		Ok(())
+	}
+
+	fn is_cold_account(&self, address: H160) -> bool {
+		!self.warm_accounts.contains(&address)
+	}
+
+	fn warm_account(&mut self, address: H160) {
+		self.warm_accounts.insert(address);
+	}
+}
@@ -0,0 +1,70 @@
+use evm_runtime::{Config, ConfigError};
+
+#[test]
+fn fork_configs_are_valid() {
+	assert_eq!(Config::frontier().validate(), Ok(()));
+	assert_eq!(Config::istanbul().validate(), Ok(()));
+	assert_eq!(Config::berlin().validate(), Ok(()));
+	assert_eq!(Config::london().validate(), Ok(()));
+	assert_eq!(Config::optimism().validate(), Ok(()));
+}
+
+#[test]
+fn create2_without_shifting_is_rejected() {
+	let config = Config { has_bitwise_shifting: false, ..Config::istanbul() };
+	assert_eq!(
+		config.validate(),
+		Err(ConfigError::Requires { flag: "has_create2", requires: "has_bitwise_shifting" }),
+	);
+}
+
+#[test]
+fn stipend_rule_without_sstore_metering_is_rejected() {
+	let config = Config { sstore_gas_metering: false, ..Config::istanbul() };
+	assert_eq!(
+		config.validate(),
+		Err(ConfigError::Requires { flag: "sstore_revert_under_stipend", requires: "sstore_gas_metering" }),
+	);
+}
+
+#[test]
+fn later_fork_flag_on_earlier_fork_is_rejected() {
+	let config = Config { has_chain_id: true, ..Config::frontier() };
+	assert_eq!(
+		config.validate(),
+		Err(ConfigError::Requires { flag: "has_chain_id", requires: "has_create2" }),
+	);
+
+	let config = Config { has_base_fee: true, ..Config::frontier() };
+	assert_eq!(
+		config.validate(),
+		Err(ConfigError::Requires { flag: "has_base_fee", requires: "has_chain_id" }),
+	);
+}
+
+#[test]
+fn cancun_flags_go_together() {
+	let config = Config { has_eip6780: true, has_base_fee: true, ..Config::istanbul() };
+	assert_eq!(
+		config.validate(),
+		Err(ConfigError::Requires { flag: "has_eip6780", requires: "has_blob_base_fee" }),
+	);
+
+	let config = Config { has_eip6780: true, has_blob_base_fee: true, has_base_fee: true, ..Config::istanbul() };
+	assert_eq!(config.validate(), Ok(()));
+}
+
+#[test]
+fn london_keeps_the_berlin_access_costs() {
+	let london = Config::london();
+	assert!(london.increase_state_access_gas);
+	assert_eq!((london.gas_sload_cold, london.gas_storage_read_warm, london.gas_account_access_cold), (2100, 100, 2600));
+	assert_eq!((london.gas_sload, london.gas_sstore_reset, london.gas_call), (0, 2900, 0));
+	assert_eq!(london.refund_sstore_clears, 4800);
+	assert!(!Config::istanbul().increase_state_access_gas);
+}
+
+#[test]
+fn optimism_is_london() {
+	assert_eq!(format!("{:?}", Config::optimism()), format!("{:?}", Config::london()));
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use common::{address, call, context, log_byte, run_to_exit, MockHandler};
+use evm_runtime::{
+	save_created_address, save_return_value, Capture, Config, Control, ExitError, ExitFatal, ExitSucceed, Opcode, Resolve,
+	Runtime, Valids, U256,
+};
+
+static ONE_LOG: Config = Config { max_logs: Some(1), ..Config::istanbul() };
+static GUARDED: Config = Config { max_backward_jumps_per_dest: Some(3), ..Config::istanbul() };
+static ALIASED: Config = Config { opcode_aliases: &[(0xb0, Opcode::SSTORE)], ..Config::istanbul() };
+
+/// Run `runtime` up to its first call interrupt, defer it and return the
+/// runtime as deserialized from its borsh encoding.
+fn pause_and_round_trip(mut runtime: Runtime, handler: &mut MockHandler) -> Runtime {
+	handler.trap = true;
+	match runtime.run(u64::MAX, handler).1 {
+		Capture::Trap(Resolve::Call(_, resolve)) => resolve.defer(),
+		_ => panic!("expected a call interrupt"),
+	}
+	handler.trap = false;
+	Runtime::try_from_slice(&runtime.try_to_vec().unwrap()).unwrap()
+}
+
+fn logging_runtime() -> Runtime {
+	let code = [call(address(0xb)), log_byte(1), log_byte(2)].concat();
+	let valids = Valids::compute(&code);
+	Runtime::new_with_config(code, valids, Vec::new(), context(address(0xa)), &ONE_LOG)
+}
+
+#[test]
+fn deserialized_runtime_refuses_to_run_without_config() {
+	let mut handler = MockHandler::new();
+	let mut runtime = pause_and_round_trip(logging_runtime(), &mut handler);
+	assert!(runtime.config().is_none());
+
+	let control = runtime.resume_with_call_result(ExitSucceed::Stopped.into(), Vec::new(), &mut handler);
+	assert!(matches!(control, Control::Exit(reason) if reason == ExitFatal::ConfigNotSet.into()));
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitFatal::ConfigNotSet.into());
+	assert!(handler.logs.is_empty());
+
+	// Nothing was changed: the runtime still resumes once configured.
+	runtime.set_config(&ONE_LOG);
+	runtime.resume_with_call_result(ExitSucceed::Stopped.into(), Vec::new(), &mut handler);
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitError::TooManyLogs.into());
+}
+
+#[test]
+fn public_resume_helpers_refuse_without_config() {
+	let mut handler = MockHandler::new();
+	let mut runtime = pause_and_round_trip(logging_runtime(), &mut handler);
+	let not_set = |control: Control<MockHandler>| matches!(control, Control::Exit(reason) if reason == ExitFatal::ConfigNotSet.into());
+
+	assert!(not_set(save_return_value(&mut runtime, ExitSucceed::Stopped.into(), Vec::new(), &mut handler)));
+	assert!(not_set(save_created_address(&mut runtime, ExitSucceed::Stopped.into(), None, Vec::new(), &mut handler)));
+	assert_eq!(runtime.finalize_gas(100_000, 15_000), Err(ExitFatal::ConfigNotSet));
+
+	// The output region of the call is still awaiting its result.
+	runtime.set_config(&ONE_LOG);
+	runtime.resume_with_call_result(ExitSucceed::Stopped.into(), Vec::new(), &mut handler);
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitError::TooManyLogs.into());
+}
+
+#[test]
+fn restored_config_applies_after_resume() {
+	let mut handler = MockHandler::new();
+	let mut runtime = pause_and_round_trip(logging_runtime(), &mut handler);
+	runtime.set_config(&ONE_LOG);
+	assert!(core::ptr::eq(runtime.config().unwrap(), &ONE_LOG));
+
+	runtime.resume_with_call_result(ExitSucceed::Stopped.into(), Vec::new(), &mut handler);
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitError::TooManyLogs.into());
+	assert_eq!(handler.logs.len(), 1);
+}
+
+#[test]
+fn restored_config_restores_opcode_aliases() {
+	// CALL b, then SSTORE(0, 7) through the alias.
+	let code = [call(address(0xb)), vec![0x60, 0x07, 0x60, 0x00, 0xb0]].concat();
+	let valids = Valids::compute(&code);
+	let runtime = Runtime::new_with_config(code, valids, Vec::new(), context(address(0xa)), &ALIASED);
+	let mut handler = MockHandler::new();
+	let mut runtime = pause_and_round_trip(runtime, &mut handler);
+	runtime.set_config(&ALIASED);
+
+	runtime.resume_with_call_result(ExitSucceed::Stopped.into(), Vec::new(), &mut handler);
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	assert_eq!(handler.storage[&(address(0xa), U256::zero())], U256::from(7));
+}
+
+#[test]
+fn loop_detection_survives_round_trips() {
+	// JUMPDEST, CALL b, POP, JUMP(0): forever, interrupted once per
+	// iteration.
+	let code = [vec![0x5b], call(address(0xb)), vec![0x50, 0x60, 0x00, 0x56]].concat();
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new_with_config(code, valids, Vec::new(), context(address(0xa)), &GUARDED);
+	let mut handler = MockHandler::new();
+
+	let mut interrupts = 0;
+	handler.trap = true;
+	let reason = loop {
+		match runtime.run(u64::MAX, &mut handler).1 {
+			Capture::Trap(Resolve::Call(_, resolve)) => resolve.defer(),
+			Capture::Trap(Resolve::Create(..)) => panic!("unexpected create interrupt"),
+			Capture::Exit(reason) => break reason,
+		}
+		interrupts += 1;
+		runtime = Runtime::try_from_slice(&runtime.try_to_vec().unwrap()).unwrap();
+		runtime.set_config(&GUARDED);
+		runtime.resume_with_call_result(ExitSucceed::Stopped.into(), Vec::new(), &mut handler);
+	};
+	assert_eq!(reason, ExitError::LoopDetected.into());
+	// The first iteration and three jumps back.
+	assert_eq!(interrupts, 4);
+}
@@ -0,0 +1,35 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{Config, ExitSucceed, CONFIG, U256};
+
+static UNLIMITED: Config = Config { create_contract_limit: None, ..Config::istanbul() };
+
+/// Create a contract whose init code returns 50KB of zeros, resolving the
+/// create through an interrupt if `trap` is set, then store the created
+/// address at slot 0.
+fn create_50kb(config: &'static Config, trap: bool) -> U256 {
+	let mut handler = MockHandler { config, gas_left: 100_000_000, trap, ..MockHandler::new() };
+	let code = vec![
+		// MSTORE(0, PUSH3 51200 PUSH1 0 RETURN)
+		0x66, 0x62, 0x00, 0xc8, 0x00, 0x60, 0x00, 0xf3, 0x60, 0x00, 0x52,
+		// CREATE(0, 25, 7)
+		0x60, 0x07, 0x60, 0x19, 0x60, 0x00, 0xf0,
+		0x60, 0x00, 0x55,
+	];
+	let (reason, _) = handler.execute(code, Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	handler.storage[&(address(0xa), U256::zero())]
+}
+
+#[test]
+fn contract_above_eip170_limit_is_rejected() {
+	assert!(create_50kb(&CONFIG, false).is_zero());
+	assert!(create_50kb(&CONFIG, true).is_zero());
+}
+
+#[test]
+fn contract_of_any_size_is_deployed_without_limit() {
+	assert!(!create_50kb(&UNLIMITED, false).is_zero());
+	assert!(!create_50kb(&UNLIMITED, true).is_zero());
+}
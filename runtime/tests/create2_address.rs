@@ -0,0 +1,65 @@
+mod common;
+
+use std::str::FromStr;
+use common::{address, context, keccak, MockHandler};
+use evm_runtime::{create_address, create2_address, ExitSucceed, H160, H256, U256};
+
+fn h160(hex: &str) -> H160 {
+	H160::from_str(hex).unwrap()
+}
+
+fn h256(hex: &str) -> H256 {
+	H256::from_str(hex).unwrap()
+}
+
+#[test]
+fn matches_eip1014_examples() {
+	let zero = H256::zero();
+	let cases = [
+		(H160::zero(), zero, vec![0x00], "4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38"),
+		(h160("deadbeef00000000000000000000000000000000"), zero, vec![0x00], "b928f69bb1d91cd65274e3c79d8986362984fda3"),
+		(
+			h160("deadbeef00000000000000000000000000000000"),
+			h256("000000000000000000000000feed000000000000000000000000000000000000"),
+			vec![0x00],
+			"d04116cdd17bebe565eb2422f2497e06cc1c9833",
+		),
+		(H160::zero(), zero, Vec::new(), "e33c0c7f7df4809055c3eba6c09cfe4baf1bd9e0"),
+		(
+			h160("00000000000000000000000000000000deadbeef"),
+			h256("00000000000000000000000000000000000000000000000000000000cafebabe"),
+			vec![0xde, 0xad, 0xbe, 0xef],
+			"60f3f640a8508fc6a86d45df051962668e1e8ac7",
+		),
+	];
+	for (caller, salt, init_code, expected) in cases {
+		assert_eq!(create2_address(caller, salt, keccak(&init_code)), h160(expected));
+	}
+}
+
+#[test]
+fn legacy_addresses_match_known_deployments() {
+	let caller = h160("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0");
+	let cases = [
+		(0, "cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d"),
+		(1, "343c43a37d37dff08ae8c4a11544c718abb4fcf8"),
+		(2, "f778b86fa74e846c4f0a1fbd1335fe81c00a0c91"),
+		(3, "fffd933a0bc612844eaf0c6fe3e5b8e9b6c1d19c"),
+	];
+	for (nonce, expected) in cases {
+		assert_eq!(create_address(caller, U256::from(nonce)), h160(expected));
+	}
+}
+
+#[test]
+fn runtime_deploys_to_the_predicted_address() {
+	let creator = address(0xa);
+	let mut handler = MockHandler::new();
+	// CREATE2(0, 0, 0, salt 7), storing the address at slot 0.
+	let code = vec![0x60, 0x07, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf5, 0x60, 0x00, 0x55];
+	let (reason, _) = handler.execute(code, Vec::new(), context(creator));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+
+	let predicted = create2_address(creator, H256::from(U256::from(7)), keccak(&[]));
+	assert_eq!(H160::from(H256::from(handler.storage[&(creator, U256::zero())])), predicted);
+}
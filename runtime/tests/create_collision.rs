@@ -0,0 +1,66 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{create_address, Config, ExitSucceed, Handler, H160, H256, U256};
+
+static UNCHECKED: Config = Config { has_create_collision_check: false, ..Config::istanbul() };
+
+/// MSTORE(0, init code returning one byte of code), then twice
+/// SSTORE(n, CREATE2(0, 27, 5, 0)).
+fn create2_twice() -> Vec<u8> {
+	let mut code = vec![0x64, 0x60, 0x01, 0x60, 0x00, 0xf3, 0x60, 0x00, 0x52];
+	for slot in 0..2 {
+		code.extend_from_slice(&[0x60, 0x00, 0x60, 0x05, 0x60, 0x1b, 0x60, 0x00, 0xf5, 0x60, slot, 0x55]);
+	}
+	code
+}
+
+fn run(config: &'static Config) -> (U256, U256) {
+	let caller = address(0xa);
+	let mut handler = MockHandler { config, ..MockHandler::new() };
+	let (reason, _) = handler.execute(create2_twice(), Vec::new(), context(caller));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	(handler.storage[&(caller, U256::zero())], handler.storage.get(&(caller, U256::one())).copied().unwrap_or_default())
+}
+
+#[test]
+fn second_create2_with_the_same_salt_and_code_fails() {
+	let (first, second) = run(&evm_runtime::CONFIG);
+	assert_ne!(first, U256::zero());
+	assert_eq!(second, U256::zero());
+}
+
+#[test]
+fn collision_check_can_be_disabled() {
+	let (first, second) = run(&UNCHECKED);
+	assert_ne!(first, U256::zero());
+	assert_eq!(second, first);
+}
+
+/// SSTORE(0, CREATE(0, 0, 0)) from a caller whose next address holds code.
+fn create_over_code(config: &'static Config) -> (MockHandler, H160) {
+	let caller = address(0xa);
+	let mut handler = MockHandler { config, ..MockHandler::new() };
+	let taken = create_address(caller, U256::zero());
+	handler.code.insert(taken, vec![0x00]);
+	let code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf0, 0x60, 0x00, 0x55];
+	let (reason, _) = handler.execute(code, Vec::new(), context(caller));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	(handler, taken)
+}
+
+#[test]
+fn create_to_an_address_with_code_fails() {
+	let (handler, _) = create_over_code(&evm_runtime::CONFIG);
+	let caller = address(0xa);
+	assert_eq!(handler.storage.get(&(caller, U256::zero())).copied().unwrap_or_default(), U256::zero());
+	// The nonce is still spent.
+	assert_eq!(handler.nonce(caller), U256::one());
+}
+
+#[test]
+fn create_collision_check_can_be_disabled() {
+	let (handler, taken) = create_over_code(&UNCHECKED);
+	let created = handler.storage[&(address(0xa), U256::zero())];
+	assert_eq!(H160::from(H256::from(created)), taken);
+}
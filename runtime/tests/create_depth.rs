@@ -0,0 +1,39 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{Config, ExitSucceed, H160, U256};
+
+static LIMITED: Config = Config { create_depth_limit: Some(3), ..Config::istanbul() };
+
+/// Init code creating a copy of itself and storing the created address at
+/// slot 0.
+fn replicating_init_code() -> Vec<u8> {
+	vec![
+		0x38, 0x60, 0x00, 0x60, 0x00, 0x39,
+		0x38, 0x60, 0x00, 0x60, 0x00, 0xf0,
+		0x60, 0x00, 0x55, 0x00,
+	]
+}
+
+#[test]
+fn nested_creates_stop_at_the_limit() {
+	let top = address(0xa);
+	let mut handler = MockHandler { config: &LIMITED, ..MockHandler::new() };
+	let (reason, _) = handler.execute(replicating_init_code(), Vec::new(), context(top));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+
+	// Follow the chain of created addresses down to the frame whose CREATE
+	// failed.
+	let mut created = 0;
+	let mut current = top;
+	loop {
+		let next = handler.storage.get(&(current, U256::zero())).copied().unwrap_or_default();
+		if next.is_zero() {
+			break;
+		}
+		created += 1;
+		current = H160::from(next);
+	}
+	assert_eq!(created, 3);
+	assert_eq!(handler.create_depth, 0);
+}
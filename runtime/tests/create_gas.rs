@@ -0,0 +1,29 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{all_but_one_64th, Config, ExitSucceed, CONFIG};
+
+static FRONTIER: Config = Config::frontier();
+
+fn create_gas(config: &'static Config) -> Option<u64> {
+	let mut handler = MockHandler { config, ..MockHandler::new() };
+	// CREATE(0, 0, 0)
+	let code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf0, 0x00];
+	let (reason, _) = handler.execute(code, Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	assert_eq!(handler.create_gas.len(), 1);
+	handler.create_gas[0]
+}
+
+#[test]
+fn create_forwards_all_but_one_64th() {
+	// Four opcodes have been charged by the time CREATE forwards its gas.
+	let available = 1_000_000 - 4;
+	assert_eq!(create_gas(&CONFIG), Some(all_but_one_64th(available)));
+	assert_eq!(all_but_one_64th(available), 984_372);
+}
+
+#[test]
+fn create_forwards_all_gas_without_l64() {
+	assert_eq!(create_gas(&FRONTIER), None);
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{create_address, ExitSucceed, U256};
+
+/// CREATE(0, 0, 0) twice, storing the addresses at slots 0 and 1.
+fn two_creates() -> Vec<u8> {
+	vec![
+		0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf0, 0x60, 0x00, 0x55,
+		0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf0, 0x60, 0x01, 0x55,
+	]
+}
+
+#[test]
+fn sequential_creates_get_different_addresses() {
+	let creator = address(0xa);
+	let mut handler = MockHandler::new();
+
+	let (reason, _) = handler.execute(two_creates(), Vec::new(), context(creator));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+
+	let first = handler.storage[&(creator, U256::zero())];
+	let second = handler.storage[&(creator, U256::one())];
+	assert_eq!(first, U256::from(create_address(creator, U256::zero()).as_bytes()));
+	assert_eq!(second, U256::from(create_address(creator, U256::one()).as_bytes()));
+	assert_eq!(handler.nonces[&creator], U256::from(2));
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn only_performed_increments_are_traced() {
+	use evm_runtime::tracing::{using, Event, EventListener};
+	use evm_runtime::H160;
+
+	#[derive(Default)]
+	struct NonceListener(Vec<H160>);
+
+	impl EventListener for NonceListener {
+		fn event(&mut self, event: Event) {
+			if let Event::IncrementNonce(trace) = event {
+				self.0.push(trace.address);
+			}
+		}
+	}
+
+	let creator = address(0xa);
+	for (skip_inc_nonce, expected) in [(false, vec![creator, creator]), (true, Vec::new())] {
+		let mut handler = MockHandler { skip_inc_nonce, ..MockHandler::new() };
+		let mut listener = NonceListener::default();
+		using(&mut listener, || {
+			handler.execute(two_creates(), Vec::new(), context(creator));
+		});
+		assert_eq!(listener.0, expected);
+	}
+}
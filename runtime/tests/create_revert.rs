@@ -0,0 +1,49 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{ExitSucceed, U256};
+
+/// CREATE2 of `init_code` (at most 32 bytes), storing RETURNDATASIZE at slot
+/// 0 and the first word of the return data at slot 1.
+fn create2_and_store_return_data(init_code: &[u8]) -> Vec<u8> {
+	let len = init_code.len() as u8;
+	let mut code = vec![0x7f];
+	code.extend_from_slice(&[0; 32][init_code.len()..]);
+	code.extend_from_slice(init_code);
+	code.extend_from_slice(&[
+		0x60, 0x00, 0x52,
+		0x60, 0x00, 0x60, len, 0x60, 32 - len, 0x60, 0x00, 0xf5, 0x50,
+		0x3d, 0x60, 0x00, 0x55,
+		0x3d, 0x60, 0x00, 0x60, 0x20, 0x3e,
+		0x60, 0x20, 0x51, 0x60, 0x01, 0x55,
+	]);
+	code
+}
+
+#[test]
+fn revert_reason_of_failed_create_is_returned() {
+	let creator = address(0xa);
+	let mut handler = MockHandler::new();
+	// REVERT with 0xdeadbeef.
+	let init_code = [0x63, 0xde, 0xad, 0xbe, 0xef, 0x60, 0x00, 0x52, 0x60, 0x04, 0x60, 0x1c, 0xfd];
+
+	let (reason, _) = handler.execute(create2_and_store_return_data(&init_code), Vec::new(), context(creator));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+
+	let mut expected = [0u8; 32];
+	expected[..4].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+	assert_eq!(handler.storage[&(creator, U256::zero())], U256::from(4));
+	assert_eq!(handler.storage[&(creator, U256::one())], U256::from_big_endian(&expected));
+}
+
+#[test]
+fn output_of_successful_create_is_not_returned() {
+	let creator = address(0xa);
+	let mut handler = MockHandler::new();
+	// RETURN a single byte of code.
+	let init_code = [0x60, 0x01, 0x60, 0x00, 0xf3];
+
+	let (reason, _) = handler.execute(create2_and_store_return_data(&init_code), Vec::new(), context(creator));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	assert_eq!(handler.storage.get(&(creator, U256::zero())).copied().unwrap_or_default(), U256::zero());
+}
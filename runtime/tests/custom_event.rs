@@ -0,0 +1,30 @@
+#![cfg(feature = "tracing")]
+
+mod common;
+
+use common::MockHandler;
+use evm_runtime::tracing::{using, Event, EventListener};
+use evm_runtime::{CachingHandler, Handler};
+
+/// `Custom` events as `(tag, data)`.
+#[derive(Default)]
+struct CustomListener(Vec<(u32, Vec<u8>)>);
+
+impl EventListener for CustomListener {
+	fn event(&mut self, event: Event) {
+		if let Event::Custom(trace) = event {
+			self.0.push((trace.tag, trace.data.to_vec()));
+		}
+	}
+}
+
+#[test]
+fn custom_events_reach_the_listener_through_decorators() {
+	let handler = CachingHandler::new(MockHandler::new());
+	let mut listener = CustomListener::default();
+	using(&mut listener, || {
+		handler.custom_event(7, &[0xde, 0xad]);
+		handler.custom_event(8, &[]);
+	});
+	assert_eq!(listener.0, vec![(7, vec![0xde, 0xad]), (8, Vec::new())]);
+}
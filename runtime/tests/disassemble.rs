@@ -0,0 +1,34 @@
+mod common;
+
+use common::{address, context};
+use evm_runtime::{Opcode, Runtime, Valids};
+
+fn disassemble(code: Vec<u8>) -> Vec<(usize, Opcode, Option<Vec<u8>>)> {
+	let valids = Valids::compute(&code);
+	Runtime::new(code, valids, Vec::new(), context(address(0xa))).disassemble()
+}
+
+#[test]
+fn push_data_is_skipped() {
+	// PUSH32 0x5b.., PUSH1 0, SSTORE, JUMPDEST, STOP
+	let mut code = vec![0x7f];
+	code.extend_from_slice(&[0x5b; 32]);
+	code.extend_from_slice(&[0x60, 0x00, 0x55, 0x5b, 0x00]);
+
+	let instructions = disassemble(code);
+	let positions: Vec<usize> = instructions.iter().map(|(position, _, _)| *position).collect();
+	assert_eq!(positions, vec![0, 33, 35, 36, 37]);
+	assert_eq!(instructions[0], (0, Opcode::PUSH32, Some(vec![0x5b; 32])));
+	assert_eq!(instructions[1], (33, Opcode::PUSH1, Some(vec![0x00])));
+	assert_eq!(instructions[2], (35, Opcode::SSTORE, None));
+	assert_eq!(instructions[3], (36, Opcode::JUMPDEST, None));
+}
+
+#[test]
+fn truncated_push_keeps_the_remaining_bytes() {
+	assert_eq!(disassemble(vec![0x00, 0x62, 0x01]), vec![
+		(0, Opcode::STOP, None),
+		(1, Opcode::PUSH3, Some(vec![0x01])),
+	]);
+	assert_eq!(disassemble(Vec::new()), Vec::new());
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::{address, call, context, MockHandler, IDENTITY};
+use evm_runtime::{delegation_designator, Config, ExitSucceed, H160, U256};
+
+static PRAGUE: Config = Config { has_eip7702: true, ..Config::istanbul() };
+
+fn designator(target: H160) -> Vec<u8> {
+	[&[0xef, 0x01, 0x00][..], target.as_bytes()].concat()
+}
+
+#[test]
+fn parses_designator() {
+	let target = address(0x1234);
+	assert_eq!(delegation_designator(&designator(target)), Some(target));
+	assert_eq!(delegation_designator(&[]), None);
+	assert_eq!(delegation_designator(&designator(target)[..22]), None);
+	assert_eq!(delegation_designator(&[designator(target), vec![0x00]].concat()), None);
+	assert_eq!(delegation_designator(&[&[0xef, 0x01, 0x01][..], target.as_bytes()].concat()), None);
+}
+
+fn call_delegating_account(config: &'static Config) -> MockHandler {
+	let (caller, eoa, target) = (address(0xa), address(0xe0a), address(0x7702));
+	let mut handler = MockHandler { config, ..MockHandler::new() };
+	handler.deploy(eoa, designator(target));
+	// SSTORE(0, ADDRESS)
+	handler.deploy(target, vec![0x30, 0x60, 0x00, 0x55]);
+
+	let (reason, _) = handler.execute(call(eoa), Vec::new(), context(caller));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	handler
+}
+
+#[test]
+fn executes_designated_code_in_delegating_context() {
+	let handler = call_delegating_account(&PRAGUE);
+	let (eoa, target) = (address(0xe0a), address(0x7702));
+
+	assert_eq!(handler.storage.get(&(eoa, U256::zero())), Some(&U256::from(0xe0a)));
+	assert!(!handler.storage.keys().any(|(address, _)| *address == target));
+}
+
+#[test]
+fn ignores_designator_without_eip7702() {
+	let handler = call_delegating_account(Config::default());
+	assert!(handler.storage.is_empty());
+}
+
+#[test]
+fn delegation_to_a_precompile_runs_empty_code() {
+	let (caller, eoa) = (address(0xa), address(0xe0a));
+	let mut handler = MockHandler { config: &PRAGUE, ..MockHandler::new() };
+	handler.deploy(eoa, designator(IDENTITY));
+
+	// MSTORE8(0, 1), SSTORE(0, CALL(GAS, eoa, 0, 0, 32, 0, 0)),
+	// SSTORE(1, RETURNDATASIZE)
+	let mut code = vec![0x60, 0x01, 0x60, 0x00, 0x53];
+	code.extend_from_slice(&[0x60, 0x00, 0x60, 0x00, 0x60, 0x20, 0x60, 0x00, 0x60, 0x00, 0x73]);
+	code.extend_from_slice(eoa.as_bytes());
+	code.extend_from_slice(&[0x5a, 0xf1, 0x60, 0x00, 0x55, 0x3d, 0x60, 0x01, 0x55]);
+
+	let (reason, _) = handler.execute(code, Vec::new(), context(caller));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	assert_eq!(handler.storage[&(caller, U256::zero())], U256::one());
+	assert_eq!(handler.storage.get(&(caller, U256::one())).copied().unwrap_or_default(), U256::zero());
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{Config, ExitSucceed, Opcode};
+
+static ESTIMATE: Config = Config { estimate: true, ..Config::istanbul() };
+
+/// PUSH1 1, PUSH1 0, SSTORE, STOP
+fn store() -> Vec<u8> {
+	vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00]
+}
+
+#[test]
+fn consistent_handler_passes_the_gas_check() {
+	let mut handler = MockHandler { config: &ESTIMATE, ..MockHandler::new() };
+	let (reason, _) = handler.execute(store(), Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+}
+
+#[test]
+fn call_returning_gas_passes_the_gas_check() {
+	let mut handler = MockHandler { config: &ESTIMATE, ..MockHandler::new() };
+	handler.deploy(address(0xb), store());
+	// CALL(gas, 0xb, 0, 0, 0, 0, 0), STOP
+	let code = vec![
+		0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00,
+		0x60, 0x0b, 0x5a, 0xf1, 0x00,
+	];
+	let (reason, _) = handler.execute(code, Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "gas left increased")]
+fn double_applied_refund_panics() {
+	let mut handler = MockHandler {
+		config: &ESTIMATE,
+		buggy_refund: Some((Opcode::SSTORE, 4800)),
+		..MockHandler::new()
+	};
+	let _ = handler.execute(store(), Vec::new(), context(address(0xa)));
+}
+
+#[test]
+fn double_applied_refund_is_ignored_outside_estimate_mode() {
+	let mut handler = MockHandler { buggy_refund: Some((Opcode::SSTORE, 4800)), ..MockHandler::new() };
+	let (reason, _) = handler.execute(store(), Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+}
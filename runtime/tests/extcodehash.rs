@@ -0,0 +1,46 @@
+mod common;
+
+use common::{address, context, keccak, MockHandler, ECRECOVER};
+use evm_runtime::{empty_code_hash, ExitSucceed, H160, H256, U256};
+
+/// EXTCODEHASH of `target`, as seen by a contract.
+fn extcodehash(handler: &mut MockHandler, target: H160) -> H256 {
+	// SSTORE(0, EXTCODEHASH(target))
+	let mut code = vec![0x73];
+	code.extend_from_slice(target.as_bytes());
+	code.extend_from_slice(&[0x3f, 0x60, 0x00, 0x55]);
+	let (reason, _) = handler.execute(code, Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	H256::from(handler.storage[&(address(0xa), U256::zero())])
+}
+
+#[test]
+fn empty_code_hash_is_keccak_of_nothing() {
+	assert_eq!(empty_code_hash(), keccak(&[]));
+}
+
+#[test]
+fn non_existent_account_hashes_to_zero() {
+	let mut handler = MockHandler::new();
+	assert_eq!(extcodehash(&mut handler, address(0xb)), H256::zero());
+}
+
+#[test]
+fn existing_account_without_code_hashes_to_empty_code_hash() {
+	let mut handler = MockHandler::new();
+	handler.balances.insert(address(0xb), U256::one());
+	assert_eq!(extcodehash(&mut handler, address(0xb)), empty_code_hash());
+}
+
+#[test]
+fn contract_hashes_its_code() {
+	let mut handler = MockHandler::new();
+	handler.deploy(address(0xb), vec![0x00]);
+	assert_eq!(extcodehash(&mut handler, address(0xb)), keccak(&[0x00]));
+}
+
+#[test]
+fn precompile_hashes_to_empty_code_hash() {
+	let mut handler = MockHandler::new();
+	assert_eq!(extcodehash(&mut handler, ECRECOVER), keccak(&[]));
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{address, call, context, run_to_exit, MockHandler};
+use evm_runtime::{Capture, Config, Control, ExitFatal, ExitReason, Resolve, Runtime, Valids, U256};
+
+static LIMITED: Config = Config { max_code_size: Some(40), ..Config::istanbul() };
+
+/// `a` calls `b`, which calls `c` whose code exceeds the limit, failing `b`
+/// fatally. `a` would store 1 at slot 0 if it continued.
+fn run_nested(trap: bool) -> (ExitReason, MockHandler) {
+	let mut handler = MockHandler { config: &LIMITED, trap, ..MockHandler::new() };
+	handler.deploy(address(0xb), call(address(0xc)));
+	handler.deploy(address(0xc), vec![0x00; 41]);
+
+	let code = [call(address(0xb)), vec![0x60, 0x01, 0x60, 0x00, 0x55]].concat();
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new_with_config(code, valids, Vec::new(), context(address(0xa)), &LIMITED);
+	let reason = run_to_exit(&mut runtime, &mut handler);
+	assert_eq!(runtime.machine().position(), &Err(reason));
+	(reason, handler)
+}
+
+#[test]
+fn fatal_child_aborts_synchronous_parent() {
+	let (reason, handler) = run_nested(false);
+	assert_eq!(reason, ExitFatal::CodeSizeLimit.into());
+	assert!(!handler.storage.contains_key(&(address(0xa), U256::zero())));
+}
+
+#[test]
+fn fatal_child_aborts_interrupted_parent() {
+	let (reason, handler) = run_nested(true);
+	assert_eq!(reason, ExitFatal::CodeSizeLimit.into());
+	assert!(!handler.storage.contains_key(&(address(0xa), U256::zero())));
+}
+
+#[test]
+fn fatal_create_result_aborts_parent() {
+	let mut handler = MockHandler { trap: true, ..MockHandler::new() };
+	// CREATE(0, 0, 0), then SSTORE(0, 1).
+	let code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf0, 0x60, 0x01, 0x60, 0x00, 0x55];
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(address(0xa)));
+
+	let control = match runtime.run(u64::MAX, &mut handler).1 {
+		Capture::Trap(Resolve::Create(_, resolve)) => {
+			resolve.finish(ExitFatal::NotSupported.into(), None, Vec::new(), &mut handler)
+		},
+		_ => panic!("expected a create interrupt"),
+	};
+	assert!(matches!(control, Control::Exit(ExitReason::Fatal(ExitFatal::NotSupported))));
+
+	handler.trap = false;
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitFatal::NotSupported.into());
+	assert!(!handler.storage.contains_key(&(address(0xa), U256::zero())));
+}
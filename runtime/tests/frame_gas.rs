@@ -0,0 +1,55 @@
+#![cfg(feature = "tracing")]
+
+mod common;
+
+use common::{address, call, context, run_to_exit, MockHandler};
+use evm_runtime::tracing::{using, Event, EventListener};
+use evm_runtime::{ExitSucceed, Runtime, Valids};
+
+/// `FrameGas` events as `(depth, gas_used, gas_used_children)`.
+#[derive(Default)]
+struct FrameGasListener(Vec<(usize, u64, u64)>);
+
+impl EventListener for FrameGasListener {
+	fn event(&mut self, event: Event) {
+		if let Event::FrameGas(trace) = event {
+			self.0.push((trace.depth, trace.gas_used, trace.gas_used_children));
+		}
+	}
+}
+
+/// Run `code` at depth 1, with the handler charging one gas per opcode.
+fn frame_gas(code: Vec<u8>, handler: &mut MockHandler) -> Vec<(usize, u64, u64)> {
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(address(0xa)));
+	runtime.set_depth(1);
+	let mut listener = FrameGasListener::default();
+	using(&mut listener, || {
+		assert_eq!(run_to_exit(&mut runtime, handler), ExitSucceed::Stopped.into());
+	});
+	listener.0
+}
+
+#[test]
+fn frame_without_children() {
+	// PUSH1 1, POP, STOP
+	assert_eq!(frame_gas(vec![0x60, 0x01, 0x50, 0x00], &mut MockHandler::new()), vec![(1, 3, 0)]);
+}
+
+#[test]
+fn children_gas_is_included_and_broken_down() {
+	// The callee runs PUSH1 1, POP, STOP.
+	let mut handler = MockHandler::new();
+	handler.deploy(address(0xb), vec![0x60, 0x01, 0x50, 0x00]);
+	let mut code = call(address(0xb));
+	code.extend_from_slice(&call(address(0xb)));
+
+	// Children run at depth 0, as the handler does not set their depth.
+	// The caller runs 2 * 8 opcodes, each callee 3.
+	let events = frame_gas(code.clone(), &mut handler);
+	assert_eq!(events, vec![(0, 3, 0), (0, 3, 0), (1, 16 + 6, 6)]);
+
+	// Resolving the calls through interrupts gives the same result.
+	handler.trap = true;
+	assert_eq!(frame_gas(code, &mut handler), events);
+}
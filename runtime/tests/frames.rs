@@ -0,0 +1,100 @@
+mod common;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use common::{address, call, context, log_byte, revert, run_to_exit, MockHandler};
+use evm_runtime::{Capture, ExitSucceed, Resolve, Runtime, Valids, U256};
+
+#[test]
+fn reverted_frames_drop_their_logs_in_order() {
+	let (a, b, c, d) = (address(0xa), address(0xb), address(0xc), address(0xd));
+	let mut handler = MockHandler::new();
+	// `b` logs, calls `c` (which logs and succeeds) and then reverts, so
+	// both its own log and the nested one from `c` must be dropped.
+	handler.deploy(b, [log_byte(2), call(c), revert()].concat());
+	handler.deploy(c, log_byte(3));
+	handler.deploy(d, log_byte(5));
+
+	let code = [log_byte(1), call(b), log_byte(4), call(d)].concat();
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(a));
+
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	let emitted: Vec<(_, u8)> = handler.logs.iter().map(|log| (log.address, log.data[0])).collect();
+	assert_eq!(emitted, vec![(a, 1), (a, 4), (d, 5)]);
+}
+
+/// Call `b` twice and CREATE(0, 0, 0) once, dropping the results.
+fn calls_and_create() -> Vec<u8> {
+	let b = address(0xb);
+	[call(b), vec![0x50], call(b), vec![0x50], vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf0, 0x50]].concat()
+}
+
+#[test]
+fn counts_spawned_call_and_create_frames() {
+	let mut handler = MockHandler::new();
+	let code = calls_and_create();
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(address(0xa)));
+
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	assert_eq!(runtime.call_frame_count(), 2);
+	assert_eq!(runtime.create_frame_count(), 1);
+	assert_eq!(runtime.frames_executed(), 3);
+	assert_eq!(runtime.interrupt_count(), 0);
+}
+
+#[test]
+fn counts_interrupts_returned_to_the_host() {
+	let mut handler = MockHandler { trap: true, ..MockHandler::new() };
+	let code = calls_and_create();
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(address(0xa)));
+
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	assert_eq!(runtime.interrupt_count(), 3);
+	assert_eq!(runtime.frames_executed(), 3);
+}
+
+/// The counts are diagnostic and left out of the serialized runtime, so
+/// that they do not change its layout.
+#[test]
+fn frame_counts_restart_after_serialization() {
+	let mut handler = MockHandler { trap: true, ..MockHandler::new() };
+	let code = calls_and_create();
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(address(0xa)));
+
+	match runtime.run(u64::MAX, &mut handler).1 {
+		Capture::Trap(Resolve::Call(_, resolve)) => resolve.defer(),
+		_ => panic!("expected a call interrupt"),
+	}
+	let bytes = runtime.try_to_vec().unwrap();
+	let restored = Runtime::try_from_slice(&bytes).unwrap();
+	assert_eq!(runtime.call_frame_count(), 1);
+	assert_eq!(restored.call_frame_count(), 0);
+	assert_eq!(restored.create_frame_count(), 0);
+	assert_eq!(runtime.interrupt_count(), 1);
+	assert_eq!(restored.interrupt_count(), 0);
+}
+
+/// Push two sentinels, then CALL `b`, which reverts, returning the parent's
+/// final stack.
+fn stack_after_reverted_call(trap: bool) -> Vec<U256> {
+	let mut handler = MockHandler { trap, ..MockHandler::new() };
+	handler.deploy(address(0xb), [log_byte(1), revert()].concat());
+
+	let code = [vec![0x60, 0xaa, 0x60, 0xbb], call(address(0xb))].concat();
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(address(0xa)));
+
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	assert!(handler.logs.is_empty());
+	runtime.machine().stack().data().to_vec()
+}
+
+#[test]
+fn reverted_call_only_replaces_its_operands_with_zero() {
+	let expected = vec![U256::from(0xaa), U256::from(0xbb), U256::zero()];
+	assert_eq!(stack_after_reverted_call(false), expected);
+	assert_eq!(stack_after_reverted_call(true), expected);
+}
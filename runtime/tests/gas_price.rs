@@ -0,0 +1,41 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{effective_gas_price, Config, CONFIG, ExitSucceed, U256};
+
+static LONDON: Config = Config { has_base_fee: true, ..Config::istanbul() };
+
+fn gas_price(config: &'static Config) -> U256 {
+	let mut handler = MockHandler {
+		config,
+		gas_price: U256::from(7),
+		base_fee: U256::from(100),
+		max_priority_fee: U256::from(2),
+		max_fee: U256::from(150),
+		..MockHandler::new()
+	};
+	// SSTORE(0, GASPRICE)
+	let (reason, _) = handler.execute(vec![0x3a, 0x60, 0x00, 0x55], Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	handler.storage[&(address(0xa), U256::zero())]
+}
+
+#[test]
+fn computes_effective_gas_price() {
+	let fee = |base: u64, priority: u64, max: u64| {
+		effective_gas_price(U256::from(base), U256::from(priority), U256::from(max))
+	};
+	assert_eq!(fee(100, 2, 150), U256::from(102));
+	assert_eq!(fee(100, 60, 150), U256::from(150));
+	assert_eq!(effective_gas_price(U256::MAX, U256::one(), U256::MAX), U256::MAX);
+}
+
+#[test]
+fn gasprice_returns_effective_price_with_base_fee() {
+	assert_eq!(gas_price(&LONDON), U256::from(102));
+}
+
+#[test]
+fn gasprice_returns_legacy_price_without_base_fee() {
+	assert_eq!(gas_price(&CONFIG), U256::from(7));
+}
@@ -0,0 +1,29 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{Capture, ExitFatal, ExitReason, Runtime, Valids};
+
+/// Run up to `max_steps`, returning the steps taken and the exit reason.
+fn run(runtime: &mut Runtime, handler: &mut MockHandler, max_steps: u64) -> (u64, ExitReason) {
+	let (steps, capture) = runtime.run(max_steps, handler);
+	let reason = match capture {
+		Capture::Exit(reason) => reason,
+		Capture::Trap(_) => panic!("unexpected interrupt"),
+	};
+	(steps, reason)
+}
+
+#[test]
+fn halted_runtime_returns_the_halt_reason() {
+	// JUMPDEST, JUMP(0): loops forever.
+	let code = vec![0x5b, 0x60, 0x00, 0x56];
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(address(0xa)));
+	let mut handler = MockHandler::new();
+
+	assert_eq!(run(&mut runtime, &mut handler, 10).1, ExitReason::StepLimitReached);
+
+	runtime.halt(ExitFatal::Killed.into());
+	assert_eq!(run(&mut runtime, &mut handler, 1), (0, ExitFatal::Killed.into()));
+	assert_eq!(runtime.machine().position(), &Err(ExitFatal::Killed.into()));
+}
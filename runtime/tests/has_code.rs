@@ -0,0 +1,27 @@
+mod common;
+
+use common::{address, call, context, MockHandler};
+use evm_runtime::{ExitSucceed, U256};
+
+/// CALL `to` and store the success flag at slot 0, returning the number of
+/// `code` calls.
+fn call_counting_code_loads(handler: &mut MockHandler, to: u64) -> usize {
+	let code = [call(address(to)), vec![0x60, 0x00, 0x55]].concat();
+	let (reason, _) = handler.execute(code, Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	assert_eq!(handler.storage[&(address(0xa), U256::zero())], U256::one());
+	handler.code_queries.get()
+}
+
+#[test]
+fn call_to_account_without_code_does_not_load_code() {
+	let mut handler = MockHandler::new();
+	assert_eq!(call_counting_code_loads(&mut handler, 0xb), 0);
+}
+
+#[test]
+fn call_to_contract_loads_its_code_once() {
+	let mut handler = MockHandler::new();
+	handler.deploy(address(0xb), vec![0x00]);
+	assert_eq!(call_counting_code_loads(&mut handler, 0xb), 1);
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{Capture, Config, ExitError, ExitReason, ExitSucceed, Runtime, Valids, CONFIG};
+
+static VIEW: Config = Config { ignore_gas: true, ..Config::istanbul() };
+
+/// Loop counting down from 1000, then stopping.
+fn countdown() -> Vec<u8> {
+	vec![0x61, 0x03, 0xe8, 0x5b, 0x60, 0x01, 0x90, 0x03, 0x80, 0x60, 0x03, 0x57, 0x00]
+}
+
+fn run(config: &'static Config) -> ExitReason {
+	let mut handler = MockHandler { config, gas_left: 100, ..MockHandler::new() };
+	let code = countdown();
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new_with_config(code, valids, Vec::new(), context(address(0xa)), config);
+	let reason = match runtime.run(100_000, &mut handler).1 {
+		Capture::Exit(reason) => reason,
+		Capture::Trap(_) => panic!("unexpected interrupt"),
+	};
+	reason
+}
+
+#[test]
+fn gas_hungry_loop_completes_when_gas_is_ignored() {
+	assert_eq!(run(&VIEW), ExitSucceed::Stopped.into());
+}
+
+#[test]
+fn gas_hungry_loop_runs_out_of_gas_otherwise() {
+	assert_eq!(run(&CONFIG), ExitError::OutOfGas.into());
+}
@@ -0,0 +1,37 @@
+#![cfg(feature = "debug-features")]
+
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{Config, ExitReason, ExitRevert, ExitSucceed, U256};
+
+static IGNORE_REVERT: Config = Config { ignore_revert: true, ..Config::istanbul() };
+
+/// SSTORE(0, 1), REVERT(0, 0), SSTORE(1, 2), STOP
+fn reverting_code() -> Vec<u8> {
+	let mut code = vec![0x60, 0x01, 0x60, 0x00, 0x55];
+	code.extend_from_slice(&common::revert());
+	code.extend_from_slice(&[0x60, 0x02, 0x60, 0x01, 0x55, 0x00]);
+	code
+}
+
+fn run(config: &'static Config) -> (ExitReason, MockHandler) {
+	let mut handler = MockHandler { config, ..MockHandler::new() };
+	let (reason, _) = handler.execute(reverting_code(), Vec::new(), context(address(0xa)));
+	(reason, handler)
+}
+
+#[test]
+fn revert_exits_by_default() {
+	let (reason, handler) = run(&evm_runtime::CONFIG);
+	assert_eq!(reason, ExitRevert::Reverted.into());
+	assert!(!handler.storage.contains_key(&(address(0xa), U256::one())));
+}
+
+#[test]
+fn ignored_revert_continues_without_rolling_back() {
+	let (reason, handler) = run(&IGNORE_REVERT);
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	assert_eq!(handler.storage[&(address(0xa), U256::zero())], U256::one());
+	assert_eq!(handler.storage[&(address(0xa), U256::one())], U256::from(2));
+}
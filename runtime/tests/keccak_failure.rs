@@ -0,0 +1,29 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{ExitFatal, ExitSucceed};
+
+fn run(code: Vec<u8>, keccak_fails: bool) -> MockHandler {
+	let mut handler = MockHandler::new();
+	handler.keccak_fails = keccak_fails;
+	let (reason, _) = handler.execute(code, Vec::new(), context(address(0xa)));
+	let expected = if keccak_fails { ExitFatal::Killed.into() } else { ExitSucceed::Stopped.into() };
+	assert_eq!(reason, expected);
+	handler
+}
+
+#[test]
+fn sha3_exits_with_the_handler_error() {
+	// SHA3(0, 0), STOP
+	let code = vec![0x60, 0x00, 0x60, 0x00, 0x20, 0x00];
+	run(code.clone(), false);
+	run(code, true);
+}
+
+#[test]
+fn create2_exits_with_the_handler_error_before_creating() {
+	// CREATE2(0, 0, 0, 0), STOP
+	let code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf5, 0x00];
+	assert_eq!(run(code.clone(), false).create_gas.len(), 1);
+	assert!(run(code, true).create_gas.is_empty());
+}
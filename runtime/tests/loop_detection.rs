@@ -0,0 +1,39 @@
+mod common;
+
+use common::{address, context, run_to_exit, MockHandler};
+use evm_runtime::{Capture, Config, ExitError, ExitReason, Runtime, Valids, U256};
+
+static GUARDED: Config = Config { max_backward_jumps_per_dest: Some(3), ..Config::istanbul() };
+
+/// PUSH1 0, JUMPDEST, PUSH1 1, ADD, PUSH1 2, JUMP: counts its iterations
+/// on the stack, forever.
+const INFINITE_LOOP: [u8; 9] = [0x60, 0x00, 0x5b, 0x60, 0x01, 0x01, 0x60, 0x02, 0x56];
+
+#[test]
+fn infinite_loop_is_detected() {
+	let code = INFINITE_LOOP.to_vec();
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new_with_config(code, valids, Vec::new(), context(address(0xa)), &GUARDED);
+	assert_eq!(run_to_exit(&mut runtime, &mut MockHandler::new()), ExitError::LoopDetected.into());
+	// The first iteration and three jumps back.
+	assert_eq!(runtime.machine().stack().peek(0), Ok(U256::from(4)));
+}
+
+#[test]
+fn forward_jumps_are_not_counted() {
+	// Four times PUSH1 n, JUMP, JUMPDEST at n, then STOP.
+	let code = vec![
+		0x60, 0x03, 0x56, 0x5b, 0x60, 0x07, 0x56, 0x5b, 0x60, 0x0b, 0x56, 0x5b, 0x60, 0x0f, 0x56, 0x5b, 0x00,
+	];
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new_with_config(code, valids, Vec::new(), context(address(0xa)), &GUARDED);
+	assert!(run_to_exit(&mut runtime, &mut MockHandler::new()).is_succeed());
+}
+
+#[test]
+fn no_detection_by_default() {
+	let code = INFINITE_LOOP.to_vec();
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(address(0xa)));
+	assert!(matches!(runtime.run(1000, &mut MockHandler::new()).1, Capture::Exit(ExitReason::StepLimitReached)));
+}
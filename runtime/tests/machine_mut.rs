@@ -0,0 +1,22 @@
+#![cfg(feature = "advanced-api")]
+
+mod common;
+
+use common::{address, context, run_to_exit, MockHandler};
+use evm_runtime::{ExitSucceed, Runtime, Valids, U256};
+
+#[test]
+fn code_sees_memory_written_by_the_embedder() {
+	// SSTORE(0, MLOAD(0))
+	let code = vec![0x60, 0x00, 0x51, 0x60, 0x00, 0x55];
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(address(0xa)));
+
+	let memory = runtime.machine_mut().memory_mut();
+	memory.resize_offset(0, 32).unwrap();
+	memory.set(31, &[9], None).unwrap();
+
+	let mut handler = MockHandler::new();
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	assert_eq!(handler.storage[&(address(0xa), U256::zero())], U256::from(9));
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{address, call, context, log_byte, MockHandler};
+use evm_runtime::{Config, ExitError, ExitSucceed, U256};
+
+static CAPPED: Config = Config { max_logs: Some(5), ..Config::istanbul() };
+
+/// Code emitting `n` empty `LOG0`s in a loop.
+fn log_loop(n: u8) -> Vec<u8> {
+	vec![
+		0x60, n, 0x5b, 0x60, 0x00, 0x60, 0x00, 0xa0,
+		0x60, 0x01, 0x90, 0x03, 0x80, 0x60, 0x02, 0x57, 0x00,
+	]
+}
+
+#[test]
+fn logs_up_to_the_cap_are_emitted() {
+	let mut handler = MockHandler { config: &CAPPED, ..MockHandler::new() };
+	let (reason, _) = handler.execute(log_loop(5), Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	assert_eq!(handler.logs.len(), 5);
+}
+
+#[test]
+fn log_past_the_cap_fails_the_frame() {
+	let mut handler = MockHandler { config: &CAPPED, ..MockHandler::new() };
+	let (reason, _) = handler.execute(log_loop(100), Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitError::TooManyLogs.into());
+	assert_eq!(handler.logs.len(), 5);
+}
+
+#[test]
+fn cap_counts_logs_across_frames() {
+	let parent = address(0xa);
+	let child = address(0xb);
+	let mut handler = MockHandler { config: &CAPPED, ..MockHandler::new() };
+	handler.deploy(child, log_loop(3));
+
+	// Three logs, CALL the child emitting three more, then store the success
+	// flag at slot 0.
+	let code = [log_byte(1), log_byte(2), log_byte(3), call(child), vec![0x60, 0x00, 0x55]].concat();
+	let (reason, _) = handler.execute(code, Vec::new(), context(parent));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+
+	// The child failed and its logs were discarded.
+	assert_eq!(handler.storage[&(parent, U256::zero())], U256::zero());
+	assert_eq!(handler.logs.len(), 3);
+}
+
+#[test]
+fn logs_are_not_capped_by_default() {
+	let mut handler = MockHandler::new();
+	let (reason, _) = handler.execute(log_loop(100), Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	assert_eq!(handler.logs.len(), 100);
+}
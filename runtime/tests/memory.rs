@@ -0,0 +1,57 @@
+mod common;
+
+use common::{address, context, run_to_exit, MockHandler};
+use evm_runtime::{memory_gas, ExitSucceed, Runtime, Valids};
+
+fn run(code: Vec<u8>) -> Runtime {
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(address(0xa)));
+	let mut handler = MockHandler::new();
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	runtime
+}
+
+/// Memory high-water mark after running `code`.
+fn high_water_mark(code: Vec<u8>) -> usize {
+	run(code).memory_high_water_mark()
+}
+
+#[test]
+fn reads_extend_the_effective_length_to_a_word() {
+	// MLOAD(0x101), POP
+	assert_eq!(high_water_mark(vec![0x61, 0x01, 0x01, 0x51, 0x50]), 0x140);
+	assert_eq!(high_water_mark(Vec::new()), 0);
+}
+
+#[test]
+fn effective_length_never_shrinks() {
+	// MLOAD(0x100), POP, MSTORE8(5, 1)
+	assert_eq!(high_water_mark(vec![0x61, 0x01, 0x00, 0x51, 0x50, 0x60, 0x01, 0x60, 0x05, 0x53]), 0x120);
+}
+
+#[test]
+fn memory_words_round_up_the_accessed_range() {
+	// MSTORE8(0x40, 1)
+	assert_eq!(run(vec![0x60, 0x01, 0x60, 0x40, 0x53]).memory_words(), 3);
+	assert_eq!(run(Vec::new()).memory_words(), 0);
+}
+
+#[test]
+fn total_memory_gas_matches_the_final_memory_size() {
+	// MSTORE8(0x40, 1)
+	assert_eq!(run(vec![0x60, 0x01, 0x60, 0x40, 0x53]).total_memory_gas(), memory_gas(3));
+	assert_eq!(run(Vec::new()).total_memory_gas(), 0);
+}
+
+#[test]
+fn memory_gas_is_quadratic() {
+	assert_eq!(memory_gas(0), 0);
+	assert_eq!(memory_gas(1), 3);
+	assert_eq!(memory_gas(100), 319);
+	assert_eq!(memory_gas(1024), 3 * 1024 + 2048);
+}
+
+#[test]
+fn memory_gas_saturates() {
+	assert_eq!(memory_gas(usize::MAX), u64::MAX);
+}
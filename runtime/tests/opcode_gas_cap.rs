@@ -0,0 +1,71 @@
+mod common;
+
+use common::{address, call, context, MockHandler};
+use evm_runtime::{Config, ExitError, ExitReason, ExitSucceed, U256};
+
+static CAPPED: Config = Config { per_opcode_gas_cap: Some(1000), ..Config::istanbul() };
+
+/// Run a `CALL` to 0xb, charged 2600 gas if 0xb is cold and 100 if warm.
+fn run_call(config: &'static Config, warm: bool) -> ExitReason {
+	let mut handler = MockHandler { config, access_gas: true, ..MockHandler::new() };
+	if warm {
+		handler.warm_accounts.insert(address(0xb));
+	}
+	handler.execute(call(address(0xb)), Vec::new(), context(address(0xa))).0
+}
+
+#[test]
+fn opcode_charged_over_the_cap_runs_out_of_gas() {
+	assert_eq!(run_call(&CAPPED, false), ExitError::OutOfGas.into());
+}
+
+#[test]
+fn opcode_charged_under_the_cap_runs() {
+	assert_eq!(run_call(&CAPPED, true), ExitSucceed::Stopped.into());
+}
+
+#[test]
+fn no_cap_by_default() {
+	assert_eq!(run_call(&evm_runtime::CONFIG, false), ExitSucceed::Stopped.into());
+}
+
+/// Run a `SELFDESTRUCT` of a funded contract to a new account, charged one
+/// gas by `pre_validate` and the new account surcharge while evaluated.
+fn run_selfdestruct(config: &'static Config) -> ExitReason {
+	let contract = address(0xa);
+	let mut handler = MockHandler { config, ..MockHandler::new() };
+	handler.balances.insert(contract, U256::from(100));
+	let mut code = vec![0x73];
+	code.extend_from_slice(address(0xb).as_bytes());
+	code.push(0xff);
+	handler.execute(code, Vec::new(), context(contract)).0
+}
+
+#[test]
+fn surcharge_counts_towards_the_cap() {
+	assert_eq!(run_selfdestruct(&CAPPED), ExitError::OutOfGas.into());
+	assert_eq!(run_selfdestruct(&evm_runtime::CONFIG), ExitSucceed::Suicided.into());
+}
+
+/// Address created by a `CREATE` deploying 100 bytes, whose deposit is
+/// charged while evaluating it, resolved through an interrupt if `trap`.
+fn create_100_bytes(config: &'static Config, trap: bool) -> U256 {
+	let code = vec![
+		// MSTORE(0, PUSH1 100 PUSH1 0 RETURN)
+		0x64, 0x60, 0x64, 0x60, 0x00, 0xf3, 0x60, 0x00, 0x52,
+		// SSTORE(0, CREATE(0, 27, 5))
+		0x60, 0x05, 0x60, 0x1b, 0x60, 0x00, 0xf0, 0x60, 0x00, 0x55,
+	];
+	let mut handler = MockHandler { config, trap, ..MockHandler::new() };
+	let (reason, _) = handler.execute(code, Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	handler.storage.get(&(address(0xa), U256::zero())).copied().unwrap_or_default()
+}
+
+#[test]
+fn code_deposit_counts_towards_the_cap() {
+	for trap in [false, true] {
+		assert!(create_100_bytes(&CAPPED, trap).is_zero());
+		assert!(!create_100_bytes(&evm_runtime::CONFIG, trap).is_zero());
+	}
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{Config, ExitError, ExitSucceed, Opcode, U256};
+
+static ALIASED: Config = Config { opcode_aliases: &[(0xb0, Opcode::SSTORE)], ..Config::istanbul() };
+
+#[test]
+fn all_covers_every_byte() {
+	let bytes: Vec<u8> = Opcode::all().map(Opcode::as_u8).collect();
+	assert_eq!(bytes, (0..=u8::MAX).collect::<Vec<_>>());
+}
+
+#[test]
+fn dispatching_any_opcode_never_panics() {
+	for opcode in Opcode::all() {
+		// Once on an empty stack, and once with enough zero arguments for
+		// any opcode.
+		for args in [0, 17] {
+			let mut code = [0x60, 0x00].repeat(args);
+			code.push(opcode.as_u8());
+
+			let mut handler = MockHandler::new();
+			handler.execute(code, Vec::new(), context(address(0xa)));
+		}
+	}
+}
+
+#[test]
+fn unassigned_opcodes_are_left_to_the_handler() {
+	// Unassigned in Istanbul, including BASEFEE and BLOBBASEFEE.
+	let unassigned = [0x0c..=0x0f, 0x1e..=0x1f, 0x21..=0x2f, 0x48..=0x4f, 0xa5..=0xef, 0xf6..=0xf9, 0xfb..=0xfc];
+	for byte in unassigned.iter().cloned().flatten() {
+		let mut code = [0x60, 0x00].repeat(17);
+		code.push(byte);
+
+		// `Handler::other` fails with `OutOfGas` by default, an exceptional
+		// halt consuming all the gas of the frame.
+		let mut handler = MockHandler::new();
+		let (reason, _) = handler.execute(code, Vec::new(), context(address(0xa)));
+		assert_eq!(reason, ExitError::OutOfGas.into(), "opcode {:#04x}", byte);
+	}
+}
+
+#[test]
+fn aliased_byte_dispatches_as_its_opcode() {
+	let mut handler = MockHandler { config: &ALIASED, ..MockHandler::new() };
+	// PUSH1 7, PUSH1 0, 0xb0 (SSTORE)
+	let (reason, _) = handler.execute(vec![0x60, 0x07, 0x60, 0x00, 0xb0], Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	assert_eq!(handler.storage[&(address(0xa), U256::zero())], U256::from(7));
+}
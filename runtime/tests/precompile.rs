@@ -0,0 +1,110 @@
+mod common;
+
+use common::{address, context, MockHandler, IDENTITY, MODEXP};
+use evm_runtime::{Config, ExitSucceed, H160, U256};
+
+/// Code calling `to` with `gas` and 32 bytes of input, storing the success
+/// flag at slot 0 and the return data size at slot 1.
+fn call_with_gas(to: H160, gas: u8) -> Vec<u8> {
+	let mut code = vec![0x60, 0x20, 0x60, 0x00, 0x60, 0x20, 0x60, 0x00, 0x60, 0x00, 0x73];
+	code.extend_from_slice(to.as_bytes());
+	code.extend_from_slice(&[0x60, gas, 0xf1, 0x60, 0x00, 0x55, 0x3d, 0x60, 0x01, 0x55]);
+	code
+}
+
+fn run(gas: u8) -> (U256, U256) {
+	let caller = address(0xa);
+	let mut handler = MockHandler::new();
+	let (reason, _) = handler.execute(call_with_gas(IDENTITY, gas), Vec::new(), context(caller));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	(handler.storage[&(caller, U256::zero())], handler.storage[&(caller, U256::one())])
+}
+
+#[test]
+fn identity_with_enough_gas_succeeds() {
+	// 15 + 3 per word of input.
+	assert_eq!(run(18), (U256::one(), U256::from(32)));
+}
+
+#[test]
+fn identity_with_insufficient_gas_fails_the_call_only() {
+	assert_eq!(run(17), (U256::zero(), U256::zero()));
+}
+
+/// SSTORE(0, CALL(gas, IDENTITY, value, 0, in_len, 0, 0))
+fn call_identity(gas: u16, value: u8, in_len: u16) -> Vec<u8> {
+	let [len_hi, len_lo] = in_len.to_be_bytes();
+	let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x61, len_hi, len_lo, 0x60, 0x00, 0x60, value, 0x73];
+	code.extend_from_slice(IDENTITY.as_bytes());
+	let [gas_hi, gas_lo] = gas.to_be_bytes();
+	code.extend_from_slice(&[0x61, gas_hi, gas_lo, 0xf1, 0x60, 0x00, 0x55]);
+	code
+}
+
+fn run_with_value(gas_left: u64, code: Vec<u8>) -> U256 {
+	let caller = address(0xa);
+	let mut handler = MockHandler::new();
+	handler.gas_left = gas_left;
+	handler.balances.insert(caller, U256::one());
+	let (reason, _) = handler.execute(code, Vec::new(), context(caller));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	handler.storage[&(caller, U256::zero())]
+}
+
+#[test]
+fn stipend_pays_for_a_value_bearing_call() {
+	assert_eq!(run_with_value(1_000_000, call_identity(0, 1, 32)), U256::one());
+	assert_eq!(run_with_value(1_000_000, call_identity(0, 0, 32)), U256::zero());
+}
+
+#[test]
+fn stipend_is_added_after_the_l64_cap() {
+	// 300 words of input cost 915 gas, more than the capped 624 forwarded
+	// out of 633 left at the call but less than that plus the stipend.
+	assert_eq!(run_with_value(640, call_identity(u16::MAX, 1, 32 * 300)), U256::one());
+	assert_eq!(run_with_value(640, call_identity(u16::MAX, 0, 32 * 300)), U256::zero());
+}
+
+static CHECKING: Config = Config { has_eip7702: true, max_code_size: Some(0x6000), ..Config::istanbul() };
+
+#[test]
+fn custom_precompile_is_run_without_looking_at_its_account() {
+	let caller = address(0xa);
+	let precompile = address(0x400);
+	let mut handler = MockHandler { config: &CHECKING, ..MockHandler::new() };
+	handler.precompiles.insert(precompile);
+
+	let (reason, _) = handler.execute(call_with_gas(precompile, 18), Vec::new(), context(caller));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	assert_eq!(handler.storage[&(caller, U256::zero())], U256::one());
+	assert_eq!(handler.storage[&(caller, U256::one())], U256::from(32));
+	assert_eq!(handler.code_queries.get(), 0);
+	assert_eq!(handler.balance_queries.get(), 0);
+}
+
+/// SSTORE(0, CALL(0xffff, MODEXP, 0, 0, 96, 0, 0)) with a base and modulus
+/// of 1 byte and an exponent of `exp_len` bytes.
+fn call_modexp(exp_len: [u8; 32]) -> Vec<u8> {
+	let mut code = vec![0x60, 0x01, 0x60, 0x00, 0x52, 0x7f];
+	code.extend_from_slice(&exp_len);
+	code.extend_from_slice(&[0x60, 0x20, 0x52, 0x60, 0x01, 0x60, 0x40, 0x52]);
+	code.extend_from_slice(&[0x60, 0x00, 0x60, 0x00, 0x60, 0x60, 0x60, 0x00, 0x60, 0x00, 0x73]);
+	code.extend_from_slice(MODEXP.as_bytes());
+	code.extend_from_slice(&[0x61, 0xff, 0xff, 0xf1, 0x60, 0x00, 0x55]);
+	code
+}
+
+#[test]
+fn rejected_precompile_input_fails_the_call_only() {
+	let caller = address(0xa);
+	let run = |exp_len| {
+		let mut handler = MockHandler::new();
+		handler.precompiles.insert(MODEXP);
+		let (reason, _) = handler.execute(call_modexp(exp_len), Vec::new(), context(caller));
+		assert_eq!(reason, ExitSucceed::Stopped.into());
+		handler.storage[&(caller, U256::zero())]
+	};
+
+	assert_eq!(run(U256::one().into()), U256::one());
+	assert_eq!(run([0xff; 32]), U256::zero());
+}
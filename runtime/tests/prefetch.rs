@@ -0,0 +1,60 @@
+mod common;
+
+use common::{address, run_to_exit, MockHandler};
+use evm_runtime::{AccessListRecorder, CachingHandler, Context, ExitSucceed, Handler, Runtime, Valids, H256, U256};
+
+fn runtime() -> Runtime {
+	let code = vec![0x00];
+	let valids = Valids::compute(&code);
+	let context = Context { address: address(0xa), caller: address(0xe0a), apparent_value: U256::zero() };
+	Runtime::new(code, valids, Vec::new(), context)
+}
+
+#[test]
+fn prefetches_sender_recipient_and_access_list() {
+	let mut runtime = runtime();
+	let mut handler = MockHandler::new();
+	let slot = H256::from(U256::from(7));
+	let access_list = vec![(address(0xb), vec![slot]), (address(0xa), Vec::new()), (address(0xc), Vec::new())];
+
+	runtime.prefetch(&handler, &access_list);
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+
+	assert_eq!(*handler.prefetched.borrow(), vec![(
+		vec![address(0xe0a), address(0xa), address(0xb), address(0xc)],
+		vec![(address(0xb), slot)],
+	)]);
+}
+
+#[test]
+fn prefetching_is_not_an_access() {
+	let handler = AccessListRecorder::new(MockHandler::new());
+	runtime().prefetch(&handler, &[(address(0xb), vec![H256::zero()])]);
+
+	assert_eq!(handler.inner().prefetched.borrow().len(), 1);
+	assert!(handler.access_list().is_empty());
+}
+
+#[test]
+fn reads_the_slots_of_each_account_in_one_batch() {
+	let handler = MockHandler::new();
+	let slots = vec![H256::from(U256::from(1)), H256::from(U256::from(2)), H256::from(U256::from(3))];
+	runtime().prefetch(&handler, &[(address(0xb), slots), (address(0xc), Vec::new())]);
+
+	assert_eq!(*handler.storage_batches.borrow(), vec![(address(0xb), vec![U256::from(1), U256::from(2), U256::from(3)])]);
+}
+
+#[test]
+fn batched_reads_fill_the_cache() {
+	let mut inner = MockHandler::new();
+	inner.storage.insert((address(0xb), U256::from(1)), U256::from(5));
+	let handler = CachingHandler::new(inner);
+	runtime().prefetch(&handler, &[(address(0xb), vec![H256::from(U256::from(1))])]);
+
+	assert_eq!(handler.storage(address(0xb), U256::from(1)), U256::from(5));
+	assert_eq!(handler.storage_batch(address(0xb), &[U256::from(1), U256::from(2)]), vec![U256::from(5), U256::zero()]);
+	assert_eq!(*handler.inner().storage_batches.borrow(), vec![
+		(address(0xb), vec![U256::from(1)]),
+		(address(0xb), vec![U256::from(2)]),
+	]);
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use common::{address, context, run_to_exit, MockHandler};
+use evm_runtime::{memory_gas, ExitSucceed, Opcode, Runtime, Valids};
+
+/// Run `code` to its end, leaving its stack and memory to preview from.
+fn stopped(code: Vec<u8>, handler: &mut MockHandler) -> Runtime {
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(address(1)));
+	assert_eq!(run_to_exit(&mut runtime, handler), ExitSucceed::Stopped.into());
+	runtime
+}
+
+#[test]
+fn preview_adds_the_memory_expansion() {
+	let mut handler = MockHandler::new();
+	// PUSH1 0x40
+	let runtime = stopped(vec![0x60, 0x40, 0x00], &mut handler);
+	assert_eq!(runtime.preview_gas_cost(Opcode::MLOAD, &handler), Some(1 + memory_gas(3)));
+	assert_eq!(runtime.preview_gas_cost(Opcode::ADD, &handler), Some(1));
+
+	// MLOAD(0), POP, PUSH1 0x40: one word is already paid for.
+	let runtime = stopped(vec![0x60, 0x00, 0x51, 0x50, 0x60, 0x40, 0x00], &mut handler);
+	assert_eq!(runtime.preview_gas_cost(Opcode::MSTORE, &handler), Some(1 + memory_gas(3) - memory_gas(1)));
+}
+
+#[test]
+fn preview_ignores_empty_ranges() {
+	let mut handler = MockHandler::new();
+	// RETURN(0x1000, 0)
+	let runtime = stopped(vec![0x60, 0x00, 0x61, 0x10, 0x00, 0x00], &mut handler);
+	assert_eq!(runtime.preview_gas_cost(Opcode::RETURN, &handler), Some(1));
+}
+
+#[test]
+fn preview_without_operands_is_unavailable() {
+	let mut handler = MockHandler::new();
+	let runtime = stopped(vec![0x00], &mut handler);
+	assert_eq!(runtime.preview_gas_cost(Opcode::MLOAD, &handler), None);
+}
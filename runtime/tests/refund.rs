@@ -0,0 +1,33 @@
+use evm_runtime::{Config, Context, Runtime, H160, U256};
+
+static ISTANBUL: Config = Config::istanbul();
+static LONDON: Config = Config::london();
+static NO_REFUND: Config = Config { max_refund_quotient: 0, ..Config::london() };
+
+fn runtime(config: &'static Config) -> Runtime {
+	let context = Context { address: H160::default(), caller: H160::default(), apparent_value: U256::zero() };
+	Runtime::new_with_config(Vec::new(), Vec::new(), Vec::new(), context, config)
+}
+
+#[test]
+fn refund_below_the_cap_is_granted() {
+	assert_eq!(runtime(&LONDON).finalize_gas(100_000, 15_000), Ok(85_000));
+	assert_eq!(runtime(&ISTANBUL).finalize_gas(100_000, 15_000), Ok(85_000));
+}
+
+#[test]
+fn refund_exceeding_the_cap_is_capped() {
+	assert_eq!(runtime(&LONDON).finalize_gas(100_000, 60_000), Ok(80_000));
+	assert_eq!(runtime(&ISTANBUL).finalize_gas(100_000, 60_000), Ok(50_000));
+}
+
+#[test]
+fn negative_refund_counts_as_zero() {
+	assert_eq!(runtime(&LONDON).finalize_gas(100_000, -4_800), Ok(100_000));
+	assert_eq!(runtime(&LONDON).finalize_gas(100_000, i64::MIN), Ok(100_000));
+}
+
+#[test]
+fn zero_quotient_grants_no_refund() {
+	assert_eq!(runtime(&NO_REFUND).finalize_gas(100_000, 15_000), Ok(100_000));
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use common::{address, MockHandler};
+use evm_runtime::{AccessListRecorder, CachingHandler, Handler, U256};
+
+/// Set, overwrite and clear a slot, checking every replaced value.
+fn check_previous_values<H: Handler>(handler: &mut H) {
+	let (a, slot) = (address(0xa), U256::one());
+	assert_eq!(handler.replace_storage(a, slot, U256::from(1)), Ok(U256::zero()));
+	assert_eq!(handler.replace_storage(a, slot, U256::from(2)), Ok(U256::from(1)));
+	assert_eq!(handler.replace_storage(a, slot, U256::from(2)), Ok(U256::from(2)));
+	assert_eq!(handler.replace_storage(a, slot, U256::zero()), Ok(U256::from(2)));
+	assert_eq!(handler.storage(a, slot), U256::zero());
+}
+
+#[test]
+fn mock_handler_returns_replaced_values() {
+	check_previous_values(&mut MockHandler::new());
+}
+
+#[test]
+fn caching_handler_returns_buffered_values() {
+	let mut handler = CachingHandler::new(MockHandler::new());
+	check_previous_values(&mut handler);
+	assert_eq!(handler.inner().storage_writes, 0);
+}
+
+#[test]
+fn access_list_recorder_forwards_replaced_values() {
+	let mut handler = AccessListRecorder::new(MockHandler::new());
+	check_previous_values(&mut handler);
+	assert_eq!(handler.inner().storage_writes, 4);
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use common::{address, call, context, run_to_exit, MockHandler};
+use evm_runtime::{Capture, Control, ExitSucceed, Resolve, Runtime, Valids, H160, U256};
+
+/// Code storing `value` at slot 0.
+fn store(value: u8) -> Vec<u8> {
+	vec![0x60, value, 0x60, 0x00, 0x55, 0x00]
+}
+
+/// Code calling `to` and storing the success flag at slot 0.
+fn call_and_store(to: H160) -> Vec<u8> {
+	[call(to), vec![0x60, 0x00, 0x55]].concat()
+}
+
+fn slot(handler: &MockHandler, address: H160) -> U256 {
+	handler.storage.get(&(address, U256::zero())).copied().unwrap_or_default()
+}
+
+#[test]
+fn resolves_nested_calls() {
+	let (a, b, c) = (address(0xa), address(0xb), address(0xc));
+	let mut handler = MockHandler { trap: true, ..MockHandler::new() };
+	handler.deploy(b, call_and_store(c));
+	handler.deploy(c, store(0x2a));
+
+	let code = call_and_store(b);
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(a));
+
+	// Every frame traps on its call and is resolved by `run_to_exit`.
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	assert_eq!(slot(&handler, a), U256::one());
+	assert_eq!(slot(&handler, b), U256::one());
+	assert_eq!(slot(&handler, c), U256::from(0x2a));
+}
+
+#[test]
+fn resolves_create() {
+	let a = address(0xa);
+	let mut handler = MockHandler { trap: true, ..MockHandler::new() };
+
+	// CREATE(0, 0, 0), then store the created address at slot 0.
+	let code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf0, 0x60, 0x00, 0x55];
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(a));
+
+	let control = match runtime.run(u64::MAX, &mut handler).1 {
+		Capture::Trap(resolve @ Resolve::Create(..)) => Runtime::resolve_interrupt_sync(resolve, &mut handler),
+		_ => panic!("expected a create interrupt"),
+	};
+	assert!(matches!(control, Control::Continue));
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	assert!(!slot(&handler, a).is_zero());
+}
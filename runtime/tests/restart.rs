@@ -0,0 +1,41 @@
+mod common;
+
+use common::{address, context, revert, run_to_exit, MockHandler};
+use evm_runtime::{ExitError, ExitRevert, ExitSucceed, Runtime, Valids, U256};
+
+fn runtime(code: Vec<u8>) -> Runtime {
+	let valids = Valids::compute(&code);
+	Runtime::new(code, valids, Vec::new(), context(address(0xa)))
+}
+
+#[test]
+fn out_of_gas_is_clearable() {
+	// SSTORE(0, 1 + 2)
+	let mut runtime = runtime(vec![0x60, 0x01, 0x60, 0x02, 0x01, 0x60, 0x00, 0x55]);
+	let mut handler = MockHandler { gas_left: 2, ..MockHandler::new() };
+
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitError::OutOfGas.into());
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitError::OutOfGas.into());
+
+	assert_eq!(runtime.clear_error(), Ok(()));
+	handler.gas_left = 100;
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	assert_eq!(handler.storage.get(&(address(0xa), U256::zero())), Some(&U256::from(3)));
+	assert_eq!(runtime.clear_error(), Err(()));
+}
+
+#[test]
+fn other_exits_are_not_clearable() {
+	let mut handler = MockHandler::new();
+
+	let mut reverted = runtime(revert());
+	assert_eq!(run_to_exit(&mut reverted, &mut handler), ExitRevert::Reverted.into());
+	assert_eq!(reverted.clear_error(), Err(()));
+
+	let mut underflow = runtime(vec![0x01]);
+	assert_eq!(run_to_exit(&mut underflow, &mut handler), ExitError::StackUnderflow.into());
+	assert_eq!(underflow.clear_error(), Err(()));
+
+	let mut fresh = runtime(vec![0x00]);
+	assert_eq!(fresh.clear_error(), Err(()));
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use common::{address, call, context, revert, run_to_exit, MockHandler};
+use evm_runtime::{Capture, ExitReason, ExitRevert, ExitSucceed, Resolve, Runtime, Valids, H160, U256};
+
+fn runtime(code: Vec<u8>) -> Runtime {
+	let valids = Valids::compute(&code);
+	Runtime::new(code, valids, Vec::new(), context(address(0xa)))
+}
+
+/// Run `runtime` up to its first interrupt and defer it.
+fn run_to_interrupt(runtime: &mut Runtime, handler: &mut MockHandler) {
+	handler.trap = true;
+	match runtime.run(u64::MAX, handler).1 {
+		Capture::Trap(Resolve::Call(_, resolve)) => resolve.defer(),
+		Capture::Trap(Resolve::Create(_, resolve)) => resolve.defer(),
+		Capture::Exit(reason) => panic!("expected an interrupt, exited with {:?}", reason),
+	}
+	handler.trap = false;
+}
+
+fn slot(handler: &MockHandler, index: u64) -> U256 {
+	handler.storage[&(address(0xa), U256::from(index))]
+}
+
+/// Call `b`, then store the success flag at slot 0 and the return data size
+/// at slot 1.
+fn call_and_store() -> Vec<u8> {
+	[call(address(0xb)), vec![0x60, 0x00, 0x55, 0x3d, 0x60, 0x01, 0x55]].concat()
+}
+
+/// CREATE(0, 0, 0), then store the created address at slot 0.
+fn create_and_store() -> Vec<u8> {
+	vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf0, 0x60, 0x00, 0x55]
+}
+
+#[test]
+fn resumes_with_successful_call() {
+	let mut handler = MockHandler::new();
+	let mut runtime = runtime(call_and_store());
+	run_to_interrupt(&mut runtime, &mut handler);
+
+	runtime.resume_with_call_result(ExitSucceed::Returned.into(), vec![1, 2, 3], &mut handler);
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	assert_eq!((slot(&handler, 0), slot(&handler, 1)), (U256::one(), U256::from(3)));
+}
+
+#[test]
+fn resumes_with_reverted_call() {
+	let mut handler = MockHandler::new();
+	let mut runtime = runtime(call_and_store());
+	run_to_interrupt(&mut runtime, &mut handler);
+
+	// Executing the child is up to the host.
+	let (reason, return_data) = handler.execute(revert(), Vec::new(), context(address(0xb)));
+	assert_eq!(reason, ExitReason::Revert(ExitRevert::Reverted));
+	runtime.resume_with_call_result(reason, return_data, &mut handler);
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	assert_eq!((slot(&handler, 0), slot(&handler, 1)), (U256::zero(), U256::zero()));
+}
+
+#[test]
+fn resume_writes_output_to_the_call_region() {
+	// CALL(gas, b, 0, 0, 0, 0x40, 4), then store MLOAD(0x40) at slot 0.
+	let mut code = vec![0x60, 0x04, 0x60, 0x40, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+	code.extend_from_slice(address(0xb).as_bytes());
+	code.extend_from_slice(&[0x5a, 0xf1, 0x50, 0x60, 0x40, 0x51, 0x60, 0x00, 0x55]);
+
+	let mut handler = MockHandler::new();
+	let mut runtime = runtime(code);
+	run_to_interrupt(&mut runtime, &mut handler);
+
+	runtime.resume_with_call_result(ExitSucceed::Returned.into(), vec![1, 2, 3, 4, 5], &mut handler);
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	let mut expected = [0u8; 32];
+	expected[..4].copy_from_slice(&[1, 2, 3, 4]);
+	assert_eq!(slot(&handler, 0), U256::from_big_endian(&expected));
+}
+
+#[test]
+fn resumes_with_successful_create() {
+	let created = address(0xc);
+	let mut handler = MockHandler::new();
+	let mut runtime = runtime(create_and_store());
+	run_to_interrupt(&mut runtime, &mut handler);
+
+	runtime.resume_with_create_result(ExitSucceed::Returned.into(), Some(created), Vec::new(), &mut handler);
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	assert_eq!(H160::from(slot(&handler, 0)), created);
+}
+
+#[test]
+fn resumes_with_reverted_create() {
+	let mut handler = MockHandler::new();
+	let mut runtime = runtime(create_and_store());
+	run_to_interrupt(&mut runtime, &mut handler);
+
+	runtime.resume_with_create_result(ExitRevert::Reverted.into(), None, Vec::new(), &mut handler);
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	assert_eq!(slot(&handler, 0), U256::zero());
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use common::{address, call, context, run_to_exit, MockHandler};
+use evm_runtime::{Config, ExitSucceed, Runtime, Valids, U256};
+
+static CAPPED: Config = Config { max_return_data: Some(32), ..Config::istanbul() };
+
+/// Code returning `len` zero bytes, `len` being below 2^24.
+fn return_zeros(len: usize) -> Vec<u8> {
+	vec![0x62, (len >> 16) as u8, (len >> 8) as u8, len as u8, 0x60, 0x00, 0xf3]
+}
+
+#[test]
+fn return_data_buffer_is_replaced_by_every_call() {
+	const MB: usize = 1 << 20;
+	let mut handler = MockHandler::new();
+	handler.deploy(address(0xb), return_zeros(MB));
+
+	// Call `b` 100 times in a loop.
+	let code = [
+		vec![0x60, 100, 0x5b],
+		call(address(0xb)),
+		vec![0x50, 0x60, 0x01, 0x90, 0x03, 0x80, 0x60, 0x02, 0x57, 0x00],
+	].concat();
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(address(0xa)));
+
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	assert_eq!(runtime.return_data().len(), MB);
+	assert!(runtime.return_data().capacity() < 2 * MB);
+}
+
+/// Call `b`, then store the success flag at slot 0 and RETURNDATASIZE at
+/// slot 1.
+fn call_and_store(handler: &mut MockHandler) {
+	let code = [call(address(0xb)), vec![0x60, 0x00, 0x55, 0x3d, 0x60, 0x01, 0x55]].concat();
+	let (reason, _) = handler.execute(code, Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+}
+
+#[test]
+fn return_data_up_to_the_limit_is_kept() {
+	let mut handler = MockHandler { config: &CAPPED, ..MockHandler::new() };
+	handler.deploy(address(0xb), return_zeros(32));
+	call_and_store(&mut handler);
+	assert_eq!(handler.storage[&(address(0xa), U256::zero())], U256::one());
+	assert_eq!(handler.storage[&(address(0xa), U256::one())], U256::from(32));
+}
+
+#[test]
+fn return_data_past_the_limit_fails_the_call() {
+	let mut handler = MockHandler { config: &CAPPED, ..MockHandler::new() };
+	// SSTORE(0, 1), then return 33 bytes.
+	handler.deploy(address(0xb), [vec![0x60, 0x01, 0x60, 0x00, 0x55], return_zeros(33)].concat());
+	call_and_store(&mut handler);
+	assert_eq!(handler.storage[&(address(0xa), U256::zero())], U256::zero());
+	assert_eq!(handler.storage[&(address(0xa), U256::one())], U256::zero());
+	assert!(!handler.storage.contains_key(&(address(0xb), U256::zero())));
+}
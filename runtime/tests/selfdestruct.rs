@@ -0,0 +1,104 @@
+mod common;
+
+use common::{address, call, context, MockHandler};
+use evm_runtime::{Config, CONFIG, ExitSucceed, H160, U256};
+
+static CANCUN: Config = Config { has_eip6780: true, ..Config::istanbul() };
+
+/// Gas used by a contract with `balance` self-destructing to `beneficiary`.
+fn selfdestruct_gas(balance: u64, beneficiary: H160, existing: bool) -> u64 {
+	let contract = address(0xa);
+	let mut handler = MockHandler::new();
+	handler.balances.insert(contract, U256::from(balance));
+	if existing {
+		handler.balances.insert(beneficiary, U256::one());
+	}
+
+	let (reason, _) = handler.execute(selfdestruct(beneficiary), Vec::new(), context(contract));
+	assert_eq!(reason, ExitSucceed::Suicided.into());
+	1_000_000 - handler.gas_left
+}
+
+/// Code self-destructing to `beneficiary`.
+fn selfdestruct(beneficiary: H160) -> Vec<u8> {
+	let mut code = vec![0x73];
+	code.extend_from_slice(beneficiary.as_bytes());
+	code.push(0xff);
+	code
+}
+
+/// Balance left to a contract with balance 100 after self-destructing to
+/// itself.
+fn balance_after_selfdestruct_to_self(config: &'static Config) -> U256 {
+	let contract = address(0xa);
+	let mut handler = MockHandler { config, ..MockHandler::new() };
+	handler.balances.insert(contract, U256::from(100));
+
+	let (reason, _) = handler.execute(selfdestruct(contract), Vec::new(), context(contract));
+	assert_eq!(reason, ExitSucceed::Suicided.into());
+	handler.balances[&contract]
+}
+
+#[test]
+fn charges_new_account_surcharge_for_fresh_beneficiary() {
+	assert_eq!(selfdestruct_gas(1, address(0xbe), false), 2 + 25000);
+}
+
+#[test]
+fn no_surcharge_for_existing_beneficiary() {
+	assert_eq!(selfdestruct_gas(1, address(0xbe), true), 2);
+}
+
+#[test]
+fn no_surcharge_without_value() {
+	assert_eq!(selfdestruct_gas(0, address(0xbe), false), 2);
+}
+
+#[test]
+fn self_beneficiary_keeps_balance_before_cancun() {
+	assert_eq!(balance_after_selfdestruct_to_self(&CONFIG), U256::from(100));
+}
+
+#[test]
+fn self_beneficiary_burns_balance_with_eip6780() {
+	assert_eq!(balance_after_selfdestruct_to_self(&CANCUN), U256::zero());
+}
+
+#[test]
+fn balance_moves_to_other_beneficiary() {
+	let contract = address(0xa);
+	let beneficiary = address(0xbe);
+	let mut handler = MockHandler { config: &CANCUN, ..MockHandler::new() };
+	handler.balances.insert(contract, U256::from(100));
+
+	let (reason, _) = handler.execute(selfdestruct(beneficiary), Vec::new(), context(contract));
+	assert_eq!(reason, ExitSucceed::Suicided.into());
+	assert_eq!(handler.balances[&contract], U256::zero());
+	assert_eq!(handler.balances[&beneficiary], U256::from(100));
+}
+
+/// Refund recorded when the contract at 0xa self-destructs from code calling
+/// it `times` times.
+fn selfdestruct_refund(config: &'static Config, times: usize) -> i64 {
+	let contract = address(0xa);
+	let mut handler = MockHandler { config, ..MockHandler::new() };
+	handler.deploy(contract, selfdestruct(address(0xbe)));
+	let code = call(contract).repeat(times);
+
+	let (reason, _) = handler.execute(code, Vec::new(), context(address(0xb)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	handler.refund
+}
+
+static LONDON: Config = Config::london();
+
+#[test]
+fn selfdestruct_refund_is_recorded_once_per_contract() {
+	assert_eq!(selfdestruct_refund(&CONFIG, 1), 24000);
+	assert_eq!(selfdestruct_refund(&CONFIG, 3), 24000);
+}
+
+#[test]
+fn selfdestruct_refund_is_removed_in_london() {
+	assert_eq!(selfdestruct_refund(&LONDON, 1), 0);
+}
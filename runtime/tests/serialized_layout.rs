@@ -0,0 +1,73 @@
+mod common;
+
+use std::collections::BTreeMap;
+use borsh::{BorshDeserialize, BorshSerialize};
+use common::{address, context, run_to_exit, MockHandler};
+use evm_runtime::{Capture, ExitSucceed, Resolve, Runtime, Valids, CONFIG, U256};
+
+/// `prefix`, CALL(gas, b, 0, 0, 0, out_offset, out_len), then
+/// SSTORE(0, MLOAD(0x40)), suspended in the call.
+fn suspended_call(handler: &mut MockHandler, prefix: &[u8], out_offset: u8, out_len: u8) -> Runtime {
+	let mut code = prefix.to_vec();
+	code.extend_from_slice(&[0x60, out_len, 0x60, out_offset, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73]);
+	code.extend_from_slice(address(0xb).as_bytes());
+	code.extend_from_slice(&[0x5a, 0xf1, 0x60, 0x40, 0x51, 0x60, 0x00, 0x55]);
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(address(0xa)));
+
+	handler.trap = true;
+	match runtime.run(u64::MAX, handler).1 {
+		Capture::Trap(Resolve::Call(_, resolve)) => resolve.defer(),
+		_ => panic!("expected a call interrupt"),
+	}
+	handler.trap = false;
+	runtime
+}
+
+/// Resume `runtime` with 5 bytes of return data, returning the word stored.
+fn resume(mut runtime: Runtime, handler: &mut MockHandler) -> U256 {
+	runtime.set_config(&CONFIG);
+	runtime.resume_with_call_result(ExitSucceed::Returned.into(), vec![1, 2, 3, 4, 5], handler);
+	assert_eq!(run_to_exit(&mut runtime, handler), ExitSucceed::Stopped.into());
+	handler.storage[&(address(0xa), U256::zero())]
+}
+
+fn output_word() -> U256 {
+	let mut expected = [0_u8; 32];
+	expected[..4].copy_from_slice(&[1, 2, 3, 4]);
+	U256::from_big_endian(&expected)
+}
+
+#[test]
+fn versioned_layout_keeps_the_call_output() {
+	let mut handler = MockHandler::new();
+	let bytes = suspended_call(&mut handler, &[], 0x40, 4).try_to_vec().unwrap();
+	assert_eq!(bytes[..5], [0xff, 0xff, 0xff, 0xff, 1]);
+
+	let runtime = Runtime::try_from_slice(&bytes).unwrap();
+	assert_eq!(resume(runtime, &mut handler), output_word());
+}
+
+#[test]
+fn legacy_layout_resumes_a_suspended_call() {
+	let mut handler = MockHandler::new();
+	// A legacy runtime left the output region on the stack, as the pushed
+	// 4 and 0x40 are.
+	let runtime = suspended_call(&mut handler, &[0x60, 0x04, 0x60, 0x40], 0, 0);
+
+	// Strip the version header and the state of version 1.
+	let bytes = runtime.try_to_vec().unwrap();
+	let version_1 = (Some((0_usize, 0_usize)), 0_u64, None::<usize>, BTreeMap::<usize, u64>::new()).try_to_vec().unwrap();
+	let legacy = &bytes[5..bytes.len() - version_1.len()];
+
+	let runtime = Runtime::try_from_slice(legacy).unwrap();
+	assert_eq!(resume(runtime, &mut handler), output_word());
+}
+
+#[test]
+fn unknown_version_is_rejected() {
+	let mut handler = MockHandler::new();
+	let mut bytes = suspended_call(&mut handler, &[], 0x40, 4).try_to_vec().unwrap();
+	bytes[4] = 2;
+	assert!(Runtime::try_from_slice(&bytes).is_err());
+}
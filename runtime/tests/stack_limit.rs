@@ -0,0 +1,38 @@
+mod common;
+
+use common::{address, context, run_to_exit, MockHandler};
+use evm_runtime::{Config, ExitError, ExitReason, ExitSucceed, Runtime, Valids};
+
+static SANDBOXED: Config = Config { stack_limit: 16, ..Config::istanbul() };
+
+/// Code pushing `count` items and stopping.
+fn pushes(count: usize) -> Vec<u8> {
+	let mut code = vec![0x58; count];
+	code.push(0x00);
+	code
+}
+
+fn run(code: Vec<u8>) -> (ExitError, usize) {
+	let mut handler = MockHandler { config: &SANDBOXED, ..MockHandler::new() };
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new_with_config(code, valids, Vec::new(), context(address(1)), &SANDBOXED);
+	let reason = run_to_exit(&mut runtime, &mut handler);
+	match reason {
+		ExitReason::Error(e) => (e, runtime.machine().stack().len()),
+		other => panic!("unexpected exit {:?}", other),
+	}
+}
+
+#[test]
+fn pushing_up_to_the_limit_succeeds() {
+	let mut handler = MockHandler { config: &SANDBOXED, ..MockHandler::new() };
+	let (reason, _) = handler.execute(pushes(16), Vec::new(), context(address(1)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+}
+
+#[test]
+fn pushing_past_the_limit_overflows() {
+	let (error, len) = run(pushes(17));
+	assert_eq!(error, ExitError::StackOverflow);
+	assert_eq!(len, 16);
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use std::collections::BTreeMap;
+use common::{address, call, context, log_byte, revert, run_to_exit, Log, MockHandler};
+use evm_runtime::{ExitReason, ExitSucceed, Runtime, Valids, H160, U256};
+
+/// Everything observable once the top-level frame exited.
+#[derive(Debug, PartialEq)]
+struct Outcome {
+	reason: ExitReason,
+	storage: BTreeMap<(H160, U256), U256>,
+	logs: Vec<Log>,
+	nonces: BTreeMap<H160, U256>,
+	code: BTreeMap<H160, Vec<u8>>,
+	return_data: Vec<u8>,
+	stack: Vec<U256>,
+	memory: Vec<u8>,
+}
+
+/// Run the nested-call scenario, with the handler executing calls and
+/// creates inline or returning interrupts resolved afterwards.
+fn run(trap: bool) -> Outcome {
+	let (a, b, c) = (address(0xa), address(0xb), address(0xc));
+	let mut handler = MockHandler { trap, ..MockHandler::new() };
+	// `c` logs, writes and reverts.
+	handler.deploy(c, [log_byte(3), vec![0x60, 0x03, 0x60, 0x00, 0x55], revert()].concat());
+	// `b` logs, writes, calls `c` storing its failure at slot 1, then
+	// returns 0x11223344.
+	handler.deploy(b, [
+		log_byte(2),
+		vec![0x60, 0x02, 0x60, 0x00, 0x55],
+		call(c),
+		vec![0x60, 0x01, 0x55],
+		vec![0x63, 0x11, 0x22, 0x33, 0x44, 0x60, 0x00, 0x52, 0x60, 0x04, 0x60, 0x1c, 0xf3],
+	].concat());
+
+	// Log, call `b`, CREATE(0, 0, 0), RETURNDATASIZE, call `b` again, then
+	// RETURNDATACOPY(0x40, 0, 4), leaving the results on the stack.
+	let code = [
+		log_byte(1),
+		call(b),
+		vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf0, 0x3d],
+		call(b),
+		vec![0x60, 0x04, 0x60, 0x00, 0x60, 0x40, 0x3e],
+	].concat();
+	let valids = Valids::compute(&code);
+	let mut runtime = Runtime::new(code, valids, Vec::new(), context(a));
+	let reason = run_to_exit(&mut runtime, &mut handler);
+
+	Outcome {
+		reason,
+		storage: handler.storage,
+		logs: handler.logs,
+		nonces: handler.nonces,
+		code: handler.code,
+		return_data: runtime.return_data().clone(),
+		stack: runtime.machine().stack().data().to_vec(),
+		memory: runtime.machine().memory().data().to_vec(),
+	}
+}
+
+#[test]
+fn synchronous_and_interrupted_calls_agree() {
+	let sync = run(false);
+	assert_eq!(sync.reason, ExitSucceed::Stopped.into());
+	assert_eq!(sync.stack.len(), 4);
+	assert_eq!(sync.return_data, vec![0x11, 0x22, 0x33, 0x44]);
+	assert_eq!(sync.logs.len(), 3);
+
+	assert_eq!(run(true), sync);
+}
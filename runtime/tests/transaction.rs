@@ -0,0 +1,41 @@
+mod common;
+
+use common::{address, run_to_exit, MockHandler};
+use evm_runtime::{ExitSucceed, Runtime, CONFIG, H160, U256};
+
+/// SSTORE(0, CALLER), SSTORE(1, ADDRESS), SSTORE(2, CALLVALUE),
+/// SSTORE(3, CALLDATASIZE), STOP
+const RECORD_CONTEXT: [u8; 17] = [
+	0x33, 0x60, 0x00, 0x55, 0x30, 0x60, 0x01, 0x55, 0x34, 0x60, 0x02, 0x55, 0x36, 0x60, 0x03, 0x55, 0x00,
+];
+
+/// Run `runtime`, returning the four slots `RECORD_CONTEXT` stored at
+/// `address`.
+fn recorded(mut runtime: Runtime, address: H160) -> [U256; 4] {
+	let mut handler = MockHandler::new();
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	[0, 1, 2, 3].map(|slot| handler.storage.get(&(address, U256::from(slot))).copied().unwrap_or_default())
+}
+
+#[test]
+fn transaction_context_and_input() {
+	let runtime = Runtime::from_transaction(
+		address(0xe0a),
+		address(0xa),
+		U256::from(9),
+		vec![1, 2, 3],
+		RECORD_CONTEXT.to_vec(),
+		&CONFIG,
+	);
+	assert_eq!(recorded(runtime, address(0xa)), [
+		U256::from(0xe0a), U256::from(0xa), U256::from(9), U256::from(3),
+	]);
+}
+
+#[test]
+fn create_transaction_runs_init_code_without_input() {
+	let runtime = Runtime::from_create_transaction(address(0xe0a), address(0xc), U256::from(9), RECORD_CONTEXT.to_vec(), &CONFIG);
+	assert_eq!(recorded(runtime, address(0xc)), [
+		U256::from(0xe0a), U256::from(0xc), U256::from(9), U256::zero(),
+	]);
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use common::{address, context, run_to_exit, MockHandler};
+use evm_runtime::{ExitSucceed, Runtime, Valids, ValidsCache, CONFIG, H256};
+
+/// Code jumping over an invalid opcode, with a PUSH hiding a JUMPDEST byte.
+fn jumping_code() -> Vec<u8> {
+	vec![0x60, 0x06, 0x56, 0x60, 0x5b, 0xfe, 0x5b, 0x00]
+}
+
+#[test]
+fn cache_hits_return_the_computed_bitmap() {
+	let code = jumping_code();
+	let mut cache = ValidsCache::new();
+
+	let first = cache.get_or_compute(H256::repeat_byte(1), &code).to_vec();
+	assert_eq!(first, Valids::compute(&code));
+	assert_eq!(cache.get_or_compute(H256::repeat_byte(1), &code), &first[..]);
+	assert_eq!(cache.len(), 1);
+
+	cache.get_or_compute(H256::repeat_byte(2), &[0x5b]);
+	assert_eq!(cache.len(), 2);
+}
+
+#[test]
+fn bounded_cache_evicts_on_a_miss() {
+	let code = jumping_code();
+	let mut cache = ValidsCache::with_limit(2);
+	for byte in 1..=3 {
+		assert_eq!(cache.get_or_compute(H256::repeat_byte(byte), &code), &Valids::compute(&code)[..]);
+	}
+	assert_eq!(cache.len(), 2);
+
+	let mut cache = ValidsCache::with_limit(0);
+	assert_eq!(cache.get_or_compute(H256::repeat_byte(1), &code), &Valids::compute(&code)[..]);
+	assert_eq!(cache.len(), 1);
+}
+
+#[test]
+fn cached_runtimes_execute_jumps() {
+	let mut cache = ValidsCache::new();
+	let mut handler = MockHandler::new();
+	for _ in 0..2 {
+		let mut runtime = Runtime::new_cached(
+			jumping_code(),
+			H256::repeat_byte(1),
+			Vec::new(),
+			context(address(1)),
+			&CONFIG,
+			&mut cache,
+		);
+		assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	}
+	assert_eq!(cache.len(), 1);
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use common::{address, context, run_to_exit, MockHandler};
+use evm_runtime::{ExitSucceed, Runtime, Valids, ValidsLengthError};
+
+/// JUMP over an invalid opcode: PUSH1 4, JUMP, INVALID, JUMPDEST, STOP.
+fn jumping_code() -> Vec<u8> {
+	vec![0x60, 0x04, 0x56, 0xfe, 0x5b, 0x00]
+}
+
+#[test]
+fn matching_valids_are_accepted() {
+	let code = jumping_code();
+	let valids = Valids::compute(&code);
+	let mut runtime = match Runtime::try_new(code, valids, Vec::new(), context(address(0xa))) {
+		Ok(runtime) => runtime,
+		Err(err) => panic!("valids rejected: {:?}", err),
+	};
+
+	let mut handler = MockHandler::new();
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+}
+
+#[test]
+fn empty_valids_are_rejected() {
+	let result = Runtime::try_new(jumping_code(), Vec::new(), Vec::new(), context(address(0xa)));
+	assert_eq!(result.err(), Some(ValidsLengthError { expected: 1, actual: 0 }));
+}
+
+#[test]
+fn oversized_valids_are_rejected() {
+	let code = vec![0x00; 9];
+	let result = Runtime::try_new(code, vec![0; 3], Vec::new(), context(address(0xa)));
+	assert_eq!(result.err(), Some(ValidsLengthError { expected: 2, actual: 3 }));
+}
+
+#[test]
+fn empty_code_needs_no_valids() {
+	assert!(Runtime::try_new(Vec::new(), Vec::new(), Vec::new(), context(address(0xa))).is_ok());
+}
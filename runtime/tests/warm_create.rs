@@ -0,0 +1,34 @@
+mod common;
+
+use common::{address, context, MockHandler};
+use evm_runtime::{ExitSucceed, H160};
+
+/// Gas used by `code` with EIP-2929 access costs charged.
+fn gas_used(code: Vec<u8>) -> u64 {
+	let mut handler = MockHandler { access_gas: true, ..MockHandler::new() };
+	let (reason, _) = handler.execute(code, Vec::new(), context(address(0xa)));
+	assert_eq!(reason, ExitSucceed::Stopped.into());
+	1_000_000 - handler.gas_left
+}
+
+/// Code calling the address stored at slot 0.
+fn call_slot_0() -> Vec<u8> {
+	vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x54, 0x5a, 0xf1]
+}
+
+#[test]
+fn call_to_created_address_is_warm() {
+	// CREATE(0, 0, 0) and store the address at slot 0.
+	let create = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf0, 0x60, 0x00, 0x55];
+	// Fifteen opcodes plus the warm access.
+	assert_eq!(gas_used([create, call_slot_0()].concat()), 15 + 100);
+}
+
+#[test]
+fn call_to_untouched_address_is_cold() {
+	// Store an address nobody touched at slot 0.
+	let mut store = vec![0x73];
+	store.extend_from_slice(H160::repeat_byte(0xcc).as_bytes());
+	store.extend_from_slice(&[0x60, 0x00, 0x55]);
+	assert_eq!(gas_used([store, call_slot_0()].concat()), 3 + 9 + 2600);
+}
@@ -0,0 +1,22 @@
+#![cfg(feature = "test-utils")]
+
+mod common;
+
+use common::{address, context, run_to_exit, MockHandler};
+use evm_runtime::{ExitSucceed, Runtime, Valids, U256};
+
+#[test]
+fn runs_on_the_given_stack_and_memory() {
+	// SSTORE(0, ADD(MLOAD(0), <stack>))
+	let code = vec![0x60, 0x00, 0x51, 0x01, 0x60, 0x00, 0x55];
+	let valids = Valids::compute(&code);
+	let mut memory = vec![0; 32];
+	memory[31] = 5;
+	let mut runtime = Runtime::with_state(code, valids, Vec::new(), context(address(0xa)), vec![U256::from(2)], &memory);
+	assert_eq!(runtime.memory_high_water_mark(), 32);
+
+	let mut handler = MockHandler::new();
+	assert_eq!(run_to_exit(&mut runtime, &mut handler), ExitSucceed::Stopped.into());
+	assert_eq!(handler.storage[&(address(0xa), U256::zero())], U256::from(7));
+	assert!(runtime.machine().stack().data().is_empty());
+}